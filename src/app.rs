@@ -35,6 +35,9 @@ impl<'w, S: Sketch> SketchApp<'w, S> {
     let window_arc = Arc::new(window);
     let wgpu =
       WGPUController::new_with_features(window_arc.clone(), features).await;
+    if let Some(settings) = S::hdr() {
+      wgpu.enable_hdr(settings);
+    }
     sketch.init(&wgpu);
     Self {
       window: window_arc,
@@ -59,14 +62,7 @@ impl<'w, S: Sketch> SketchApp<'w, S> {
   fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
     let width = new_size.width;
     let height = new_size.height;
-    if width > 0 && height > 0 {
-      self.wgpu.config.width = width;
-      self.wgpu.config.height = height;
-      self
-        .wgpu
-        .surface
-        .configure(&self.wgpu.device, &self.wgpu.config);
-    }
+    self.wgpu.resize_surface(width, height);
     self.surface_pixel_dimensions = [width, height];
   }
   fn update(&mut self) {
@@ -108,6 +104,8 @@ impl<S: Sketch> ApplicationHandler for SketchRunner<'_, S> {
       let frame = |app: &mut SketchApp<'_, S>| match app
         .wgpu
         .surface
+        .as_ref()
+        .expect("sketches need a controller with a surface")
         .get_current_texture()
       {
         Err(err) => Err(err),
@@ -160,8 +158,12 @@ impl<S: Sketch> ApplicationHandler for SketchRunner<'_, S> {
             }
             Err(err) => panic!("{err:?}"),
             Ok((surface_texture, surface_view, frame_data)) => {
-              app.sketch.update(&app.wgpu, surface_view, frame_data);
+              app.wgpu.begin_frame();
+              let scene_view = app.wgpu.scene_view(&surface_view);
+              app.sketch.update(&app.wgpu, scene_view, frame_data);
+              app.wgpu.finish_scene(&surface_view);
               surface_texture.present();
+              app.wgpu.end_frame();
               app.frame_index += 1;
               app.scroll_delta = [0., 0.];
             }
@@ -0,0 +1,7 @@
+const WORKGROUP_SIZE: u32 = 256u;
+
+// Large dispatches are spread over a 2D grid of workgroups, see
+// `workgroup_grid`.
+fn workgroup_index(workgroup_id: vec3u, num_workgroups: vec3u) -> u32 {
+  return workgroup_id.x + workgroup_id.y * num_workgroups.x;
+}
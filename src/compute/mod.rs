@@ -0,0 +1,115 @@
+mod radix_sort;
+mod reduce;
+mod scan;
+#[cfg(test)]
+mod tests;
+
+pub use radix_sort::RadixSort;
+pub use reduce::Reduce;
+pub use reduce::ReduceOp;
+pub use scan::ExclusiveScan;
+
+use std::{cell::RefCell, num::NonZero};
+
+use bytemuck::NoUninit;
+
+use crate::wgpu::{
+  buffer::{ArrayBuffer, ArrayBufferBuilder, GpuBuffer, Storage},
+  controller::WGPUController,
+};
+
+const WORKGROUP_SIZE: u32 = 256;
+
+pub trait ComputeElement: NoUninit {
+  const WGSL_TYPE: &'static str;
+  // Maps the element's bits to a u32 that sorts in the same order.
+  const WGSL_SORT_KEY: &'static str;
+}
+
+impl ComputeElement for u32 {
+  const WGSL_TYPE: &'static str = "u32";
+  const WGSL_SORT_KEY: &'static str = "bits";
+}
+
+impl ComputeElement for i32 {
+  const WGSL_TYPE: &'static str = "i32";
+  const WGSL_SORT_KEY: &'static str = "bits ^ 0x80000000u";
+}
+
+impl ComputeElement for f32 {
+  const WGSL_TYPE: &'static str = "f32";
+  const WGSL_SORT_KEY: &'static str =
+    "bits ^ select(0x80000000u, 0xffffffffu, (bits & 0x80000000u) != 0u)";
+}
+
+fn shader_prefix<T: ComputeElement>(definitions: &str) -> String {
+  format!(
+    "alias Element = {};\n{}\n{}\n",
+    T::WGSL_TYPE,
+    definitions,
+    include_str!("common.wgsl")
+  )
+}
+
+fn block_count(len: usize) -> usize {
+  len.div_ceil(WORKGROUP_SIZE as usize)
+}
+
+// Splits a dispatch of `workgroups` over a 2D grid when it exceeds the
+// per-dimension workgroup limit. Shaders recover the flat index with
+// `workgroup_index`.
+fn workgroup_grid(wgpu: &WGPUController, workgroups: usize) -> [u32; 2] {
+  let max = wgpu.device.limits().max_compute_workgroups_per_dimension as usize;
+  let x = workgroups.clamp(1, max);
+  let y = workgroups.div_ceil(x).max(1);
+  assert!(
+    y <= max,
+    "compute dispatch of {workgroups} workgroups exceeds the device limits"
+  );
+  [x as u32, y as u32]
+}
+
+// The binding size covering the first `len` elements of a buffer, so that
+// `arrayLength` in shaders sees `len` even when the buffer is longer.
+fn binding_size<T>(len: usize) -> Option<NonZero<u64>> {
+  NonZero::new((len.max(1) * std::mem::size_of::<T>()) as u64)
+}
+
+// Storage buffers kept between recordings, one per slot. A slot is only
+// reallocated when a recording needs more elements than it has, and should be
+// bound with `binding_size`.
+struct ScratchBuffers<T: NoUninit> {
+  label: &'static str,
+  buffers: RefCell<Vec<ArrayBuffer<T, Storage>>>,
+}
+
+impl<T: NoUninit> ScratchBuffers<T> {
+  fn new(label: &'static str) -> Self {
+    Self {
+      label,
+      buffers: RefCell::new(vec![]),
+    }
+  }
+  #[track_caller]
+  fn get(
+    &self,
+    wgpu: &WGPUController,
+    slot: usize,
+    len: usize,
+  ) -> wgpu::Buffer {
+    let build = || {
+      ArrayBufferBuilder::empty(wgpu, len.max(1))
+        .with_label(self.label)
+        .with_usage_type()
+        .build()
+    };
+    let mut buffers = self.buffers.borrow_mut();
+    while buffers.len() <= slot {
+      buffers.push(build());
+    }
+    if buffers[slot].len() < len {
+      buffers[slot] = build();
+    }
+    (*buffers[slot]).clone()
+  }
+}
@@ -0,0 +1,240 @@
+use std::{marker::PhantomData, num::NonZero};
+
+use bytemuck::{NoUninit, Pod, Zeroable};
+use wgpu::{ComputePipeline, DynamicOffset};
+
+use crate::{
+  include_prefixed_wgsl,
+  wgpu::{
+    bind::BindGroupLayout,
+    buffer::{ArrayBuffer, GpuBuffer, Storage, Supports, Uniform},
+    controller::WGPUController,
+    encoder::CommandEncoder,
+  },
+};
+
+use super::{
+  binding_size, block_count, shader_prefix, workgroup_grid, ComputeElement,
+  ExclusiveScan, ScratchBuffers,
+};
+
+const RADIX_BITS: u32 = 4;
+const RADIX: usize = 1 << RADIX_BITS;
+const PASSES: u32 = u32::BITS / RADIX_BITS;
+
+const KEYS_SLOT: usize = 0;
+const VALUES_SLOT: usize = 1;
+const UNUSED_VALUES_SLOT: usize = 2;
+const HISTOGRAMS_SLOT: usize = 3;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Params {
+  shift: u32,
+  has_values: u32,
+}
+
+fn params_size() -> NonZero<u64> {
+  NonZero::new(std::mem::size_of::<Params>() as u64).unwrap()
+}
+
+fn params_block(pass: u32, has_values: bool) -> usize {
+  has_values as usize * PASSES as usize + pass as usize
+}
+
+// Stable least-significant-digit radix sort over 32-bit keys, optionally
+// carrying a 32-bit value along with each key. Every pass histograms one digit
+// per block, scans the histograms and scatters into a scratch buffer. The pass
+// count is even, so the sorted data always ends up back in the input buffers.
+// The per-pass parameters live in one uniform buffer selected with dynamic
+// offsets, and the scratch buffers are reused between recordings.
+pub struct RadixSort<K: ComputeElement> {
+  _phantom: PhantomData<K>,
+  layout: BindGroupLayout,
+  count_pipeline: ComputePipeline,
+  scatter_pipeline: ComputePipeline,
+  scan: ExclusiveScan<u32>,
+  params: ArrayBuffer<u32, Uniform>,
+  params_stride: u64,
+  scratch: ScratchBuffers<u32>,
+}
+
+impl<K: ComputeElement> RadixSort<K> {
+  pub fn new(wgpu: &WGPUController) -> Self {
+    Self::new_with_capacity(wgpu, 0)
+  }
+  // Allocates the scratch buffers for sorts of up to `capacity` keys and
+  // values up front. Longer sorts grow them when they're recorded.
+  pub fn new_with_capacity(wgpu: &WGPUController, capacity: usize) -> Self {
+    assert_eq!(
+      std::mem::size_of::<K>(),
+      4,
+      "RadixSort only supports 32-bit keys"
+    );
+    let layout = wgpu
+      .build_bind_group_layout()
+      .with_label("radix sort")
+      .with_dynamic_uniform_entry(params_size())
+      .with_read_only_storage_entry()
+      .with_compute_writable_storage_entry()
+      .with_read_only_storage_entry()
+      .with_compute_writable_storage_entry()
+      .with_compute_writable_storage_entry()
+      .build();
+    let shader = wgpu.shader(include_prefixed_wgsl!(
+      "radix_sort.wgsl",
+      shader_prefix::<K>(&format!(
+        "fn sort_key(bits: u32) -> u32 {{ return {}; }}",
+        K::WGSL_SORT_KEY
+      ))
+    ));
+    let pipeline = |entry_point| {
+      wgpu
+        .build_compute_pipeline()
+        .with_label("radix sort")
+        .add_bind_group_layout(&layout)
+        .build_with_shader_entry_point(&shader, Some(entry_point))
+    };
+    // One block of parameters for every pass, with and without values.
+    let params_stride = params_size().get().next_multiple_of(
+      wgpu.device.limits().min_uniform_buffer_offset_alignment as u64,
+    );
+    let stride_words = (params_stride / 4) as usize;
+    let mut params = vec![0; stride_words * 2 * PASSES as usize];
+    for has_values in 0..2 {
+      for pass in 0..PASSES {
+        let block = params_block(pass, has_values != 0);
+        let start = block * stride_words;
+        params[start..start + 2].copy_from_slice(bytemuck::cast_slice(&[
+          Params {
+            shift: pass * RADIX_BITS,
+            has_values,
+          },
+        ]));
+      }
+    }
+    let blocks = block_count(capacity);
+    let sort = Self {
+      _phantom: PhantomData,
+      count_pipeline: pipeline("count_digits"),
+      scatter_pipeline: pipeline("scatter"),
+      scan: ExclusiveScan::new_with_capacity(wgpu, RADIX * blocks),
+      params: wgpu
+        .build_array_buffer(&params)
+        .with_label("radix sort params")
+        .with_usage_type()
+        .build(),
+      params_stride,
+      layout,
+      scratch: ScratchBuffers::new("radix sort scratch"),
+    };
+    if capacity > 1 {
+      sort.scratch.get(wgpu, KEYS_SLOT, capacity);
+      sort.scratch.get(wgpu, VALUES_SLOT, capacity);
+      sort.scratch.get(wgpu, UNUSED_VALUES_SLOT, 1);
+      sort.scratch.get(wgpu, HISTOGRAMS_SLOT, RADIX * blocks);
+    }
+    sort
+  }
+  pub fn record<U: Supports<Storage>>(
+    &self,
+    wgpu: &WGPUController,
+    encoder: &mut CommandEncoder,
+    keys: &ArrayBuffer<K, U>,
+  ) {
+    self.record_passes(wgpu, encoder, keys, keys.len(), None);
+  }
+  pub fn record_with_values<
+    V: NoUninit,
+    U: Supports<Storage>,
+    W: Supports<Storage>,
+  >(
+    &self,
+    wgpu: &WGPUController,
+    encoder: &mut CommandEncoder,
+    keys: &ArrayBuffer<K, U>,
+    values: &ArrayBuffer<V, W>,
+  ) {
+    assert_eq!(
+      std::mem::size_of::<V>(),
+      4,
+      "RadixSort only supports 32-bit values"
+    );
+    assert_eq!(
+      keys.len(),
+      values.len(),
+      "RadixSort needs exactly one value per key"
+    );
+    self.record_passes(wgpu, encoder, keys, keys.len(), Some(values));
+  }
+  fn record_passes(
+    &self,
+    wgpu: &WGPUController,
+    encoder: &mut CommandEncoder,
+    keys: &wgpu::Buffer,
+    len: usize,
+    values: Option<&wgpu::Buffer>,
+  ) {
+    if len <= 1 {
+      return;
+    }
+    let blocks = block_count(len);
+    let has_values = values.is_some();
+    let values_len = if has_values { len } else { 1 };
+    let scratch_keys = self.scratch.get(wgpu, KEYS_SLOT, len);
+    let scratch_values = self.scratch.get(wgpu, VALUES_SLOT, values_len);
+    let unused_values = self.scratch.get(wgpu, UNUSED_VALUES_SLOT, 1);
+    let values = values.unwrap_or(&unused_values);
+    let histograms_len = RADIX * blocks;
+    let histograms = self.scratch.get(wgpu, HISTOGRAMS_SLOT, histograms_len);
+    // Even passes sort from the input buffers into the scratch buffers and
+    // odd passes sort back.
+    let bind_groups = [
+      (keys, &scratch_keys, values, &scratch_values),
+      (&scratch_keys, keys, &scratch_values, values),
+    ]
+    .map(|(keys_in, keys_out, values_in, values_out)| {
+      self
+        .layout
+        .build_group(wgpu)
+        .with_buffer_binding_entry(&self.params, 0, Some(params_size()))
+        .with_buffer_binding_entry(keys_in, 0, binding_size::<u32>(len))
+        .with_buffer_binding_entry(keys_out, 0, binding_size::<u32>(len))
+        .with_buffer_binding_entry(
+          values_in,
+          0,
+          binding_size::<u32>(values_len),
+        )
+        .with_buffer_binding_entry(
+          values_out,
+          0,
+          binding_size::<u32>(values_len),
+        )
+        .with_buffer_binding_entry(
+          &histograms,
+          0,
+          binding_size::<u32>(histograms_len),
+        )
+        .build()
+    });
+    let [x, y] = workgroup_grid(wgpu, blocks);
+    for pass in 0..PASSES {
+      let bind_group = &bind_groups[pass as usize % 2];
+      let offset = (params_block(pass, has_values) as u64 * self.params_stride)
+        as DynamicOffset;
+      encoder
+        .compute_pass()
+        .with_pipeline(&self.count_pipeline)
+        .with_offset_bind_group(0, bind_group, &[offset])
+        .dispatch(x, y, 1);
+      self
+        .scan
+        .record_raw(wgpu, encoder, &histograms, histograms_len);
+      encoder
+        .compute_pass()
+        .with_pipeline(&self.scatter_pipeline)
+        .with_offset_bind_group(0, bind_group, &[offset])
+        .dispatch(x, y, 1);
+    }
+  }
+}
@@ -0,0 +1,82 @@
+const RADIX: u32 = 16u;
+
+struct Params {
+  shift: u32,
+  has_values: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> keys_in: array<u32>;
+@group(0) @binding(2) var<storage, read_write> keys_out: array<u32>;
+@group(0) @binding(3) var<storage, read> values_in: array<u32>;
+@group(0) @binding(4) var<storage, read_write> values_out: array<u32>;
+@group(0) @binding(5) var<storage, read_write> histograms: array<u32>;
+
+var<workgroup> digit_counts: array<atomic<u32>, RADIX>;
+var<workgroup> digits: array<u32, WORKGROUP_SIZE>;
+
+fn digit_of(key: u32) -> u32 {
+  return (sort_key(key) >> params.shift) & (RADIX - 1u);
+}
+
+fn block_count() -> u32 {
+  return (arrayLength(&keys_in) + WORKGROUP_SIZE - 1u) / WORKGROUP_SIZE;
+}
+
+// Histograms are stored digit-major, so that an exclusive scan over them gives
+// every block the output offset of each of its digits.
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn count_digits(
+  @builtin(local_invocation_index) local_index: u32,
+  @builtin(workgroup_id) workgroup_id: vec3u,
+  @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+  let block = workgroup_index(workgroup_id, num_workgroups);
+  let index = block * WORKGROUP_SIZE + local_index;
+  if local_index < RADIX {
+    atomicStore(&digit_counts[local_index], 0u);
+  }
+  workgroupBarrier();
+  if index < arrayLength(&keys_in) {
+    atomicAdd(&digit_counts[digit_of(keys_in[index])], 1u);
+  }
+  workgroupBarrier();
+  let blocks = block_count();
+  if local_index < RADIX && block < blocks {
+    histograms[local_index * blocks + block] =
+      atomicLoad(&digit_counts[local_index]);
+  }
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn scatter(
+  @builtin(local_invocation_index) local_index: u32,
+  @builtin(workgroup_id) workgroup_id: vec3u,
+  @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+  let block = workgroup_index(workgroup_id, num_workgroups);
+  let index = block * WORKGROUP_SIZE + local_index;
+  let len = arrayLength(&keys_in);
+  var key = 0u;
+  var digit = RADIX;
+  if index < len {
+    key = keys_in[index];
+    digit = digit_of(key);
+  }
+  digits[local_index] = digit;
+  workgroupBarrier();
+  if index < len {
+    // Counting equal digits earlier in the block keeps the sort stable.
+    var rank = 0u;
+    for (var i = 0u; i < local_index; i++) {
+      if digits[i] == digit {
+        rank++;
+      }
+    }
+    let destination = histograms[digit * block_count() + block] + rank;
+    keys_out[destination] = key;
+    if params.has_values != 0u {
+      values_out[destination] = values_in[index];
+    }
+  }
+}
@@ -0,0 +1,152 @@
+use std::marker::PhantomData;
+
+use wgpu::ComputePipeline;
+
+use crate::{
+  include_prefixed_wgsl,
+  wgpu::{
+    bind::BindGroupLayout,
+    buffer::{ArrayBuffer, GpuBuffer, Storage, Supports},
+    controller::WGPUController,
+    encoder::CommandEncoder,
+  },
+};
+
+use super::{
+  binding_size, block_count, shader_prefix, workgroup_grid, ComputeElement,
+  ScratchBuffers,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReduceOp {
+  Sum,
+  Min,
+  Max,
+}
+
+impl ReduceOp {
+  fn wgsl(self) -> &'static str {
+    match self {
+      ReduceOp::Sum => {
+        "fn combine(a: Element, b: Element) -> Element { return a + b; }
+fn missing(first: Element) -> Element { return Element(0); }"
+      }
+      ReduceOp::Min => {
+        "fn combine(a: Element, b: Element) -> Element { return min(a, b); }
+fn missing(first: Element) -> Element { return first; }"
+      }
+      ReduceOp::Max => {
+        "fn combine(a: Element, b: Element) -> Element { return max(a, b); }
+fn missing(first: Element) -> Element { return first; }"
+      }
+    }
+  }
+}
+
+// Reduces an array to a single value, written to the first element of the
+// output buffer. Each level reduces blocks of one workgroup into a scratch
+// buffer until a single block remains. The scratch buffers are reused between
+// recordings.
+pub struct Reduce<T: ComputeElement> {
+  _phantom: PhantomData<T>,
+  op: ReduceOp,
+  layout: BindGroupLayout,
+  pipeline: ComputePipeline,
+  partials: ScratchBuffers<T>,
+}
+
+impl<T: ComputeElement> Reduce<T> {
+  pub fn new(wgpu: &WGPUController, op: ReduceOp) -> Self {
+    Self::new_with_capacity(wgpu, op, 0)
+  }
+  // Allocates the scratch buffers for inputs of up to `capacity` elements up
+  // front. Longer inputs grow them when they're recorded.
+  pub fn new_with_capacity(
+    wgpu: &WGPUController,
+    op: ReduceOp,
+    capacity: usize,
+  ) -> Self {
+    let layout = wgpu
+      .build_bind_group_layout()
+      .with_label("reduce")
+      .with_read_only_storage_entry()
+      .with_compute_writable_storage_entry()
+      .build();
+    let pipeline = wgpu
+      .build_compute_pipeline()
+      .with_label("reduce")
+      .add_bind_group_layout(&layout)
+      .build_with_shader(&wgpu.shader(include_prefixed_wgsl!(
+        "reduce.wgsl",
+        shader_prefix::<T>(op.wgsl())
+      )));
+    let reduce = Self {
+      _phantom: PhantomData,
+      op,
+      layout,
+      pipeline,
+      partials: ScratchBuffers::new("reduce partials"),
+    };
+    let (mut len, mut level) = (capacity, 0);
+    while block_count(len) > 1 {
+      len = block_count(len);
+      reduce.partials.get(wgpu, level, len);
+      level += 1;
+    }
+    reduce
+  }
+  pub fn op(&self) -> ReduceOp {
+    self.op
+  }
+  #[track_caller]
+  pub fn record<U: Supports<Storage>, V: Supports<Storage>>(
+    &self,
+    wgpu: &WGPUController,
+    encoder: &mut CommandEncoder,
+    input: &ArrayBuffer<T, U>,
+    output: &ArrayBuffer<T, V>,
+  ) {
+    // An empty sum is zero, and zeroed bits are zero for every element type.
+    // Min and max have no identity that's meaningful to the caller, so they
+    // need at least one element.
+    if input.is_empty() {
+      assert!(
+        self.op == ReduceOp::Sum,
+        "{:?} reduction of an empty buffer has no result",
+        self.op
+      );
+      encoder.clear_buffer_range(output.range(0..1));
+      return;
+    }
+    let mut len = input.len();
+    let mut level_input: Option<wgpu::Buffer> = None;
+    for level in 0.. {
+      let blocks = block_count(len);
+      let level_output =
+        (blocks > 1).then(|| self.partials.get(wgpu, level, blocks));
+      let bind_group = self
+        .layout
+        .build_group(wgpu)
+        .with_buffer_binding_entry(
+          level_input.as_ref().unwrap_or(input),
+          0,
+          binding_size::<T>(len),
+        )
+        .with_buffer_entry(level_output.as_ref().unwrap_or(output))
+        .build();
+      let [x, y] = workgroup_grid(wgpu, blocks);
+      encoder
+        .compute_pass()
+        .with_pipeline(&self.pipeline)
+        .with_bind_group(0, &bind_group)
+        .dispatch(x, y, 1);
+      match level_output {
+        Some(buffer) => {
+          level_input = Some(buffer);
+          len = blocks;
+        }
+        None => return,
+      }
+    }
+  }
+}
@@ -0,0 +1,32 @@
+@group(0) @binding(0) var<storage, read> input: array<Element>;
+@group(0) @binding(1) var<storage, read_write> output: array<Element>;
+
+var<workgroup> partials: array<Element, WORKGROUP_SIZE>;
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn reduce(
+  @builtin(local_invocation_index) local_index: u32,
+  @builtin(workgroup_id) workgroup_id: vec3u,
+  @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+  let block = workgroup_index(workgroup_id, num_workgroups);
+  let block_start = block * WORKGROUP_SIZE;
+  let index = block_start + local_index;
+  let len = arrayLength(&input);
+  var value = missing(input[min(block_start, len - 1u)]);
+  if index < len {
+    value = input[index];
+  }
+  partials[local_index] = value;
+  workgroupBarrier();
+  for (var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride >>= 1u) {
+    if local_index < stride {
+      partials[local_index] =
+        combine(partials[local_index], partials[local_index + stride]);
+    }
+    workgroupBarrier();
+  }
+  if local_index == 0u && block_start < len {
+    output[block] = partials[0];
+  }
+}
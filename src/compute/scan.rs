@@ -0,0 +1,121 @@
+use std::marker::PhantomData;
+
+use wgpu::ComputePipeline;
+
+use crate::{
+  include_prefixed_wgsl,
+  wgpu::{
+    bind::BindGroupLayout,
+    buffer::{ArrayBuffer, GpuBuffer, Storage, Supports},
+    controller::WGPUController,
+    encoder::CommandEncoder,
+  },
+};
+
+use super::{
+  binding_size, block_count, shader_prefix, workgroup_grid, ComputeElement,
+  ScratchBuffers,
+};
+
+// In-place exclusive prefix sum. Arrays longer than one workgroup are handled
+// by recursively scanning the per-block sums, which are kept in scratch
+// buffers that are reused between recordings.
+pub struct ExclusiveScan<T: ComputeElement> {
+  _phantom: PhantomData<T>,
+  layout: BindGroupLayout,
+  scan_blocks_pipeline: ComputePipeline,
+  add_block_sums_pipeline: ComputePipeline,
+  block_sums: ScratchBuffers<T>,
+}
+
+impl<T: ComputeElement> ExclusiveScan<T> {
+  pub fn new(wgpu: &WGPUController) -> Self {
+    Self::new_with_capacity(wgpu, 0)
+  }
+  // Allocates the scratch buffers for arrays of up to `capacity` elements up
+  // front. Longer arrays grow them when they're recorded.
+  pub fn new_with_capacity(wgpu: &WGPUController, capacity: usize) -> Self {
+    let layout = wgpu
+      .build_bind_group_layout()
+      .with_label("exclusive scan")
+      .with_compute_writable_storage_entry()
+      .with_compute_writable_storage_entry()
+      .build();
+    let shader =
+      wgpu.shader(include_prefixed_wgsl!("scan.wgsl", shader_prefix::<T>("")));
+    let pipeline = |entry_point| {
+      wgpu
+        .build_compute_pipeline()
+        .with_label("exclusive scan")
+        .add_bind_group_layout(&layout)
+        .build_with_shader_entry_point(&shader, Some(entry_point))
+    };
+    let scan = Self {
+      _phantom: PhantomData,
+      scan_blocks_pipeline: pipeline("scan_blocks"),
+      add_block_sums_pipeline: pipeline("add_block_sums"),
+      layout,
+      block_sums: ScratchBuffers::new("exclusive scan block sums"),
+    };
+    let (mut len, mut level) = (capacity, 0);
+    while len > 0 {
+      let blocks = block_count(len);
+      scan.block_sums.get(wgpu, level, blocks);
+      len = if blocks > 1 { blocks } else { 0 };
+      level += 1;
+    }
+    scan
+  }
+  pub fn record<U: Supports<Storage>>(
+    &self,
+    wgpu: &WGPUController,
+    encoder: &mut CommandEncoder,
+    data: &ArrayBuffer<T, U>,
+  ) {
+    self.record_raw(wgpu, encoder, data, data.len());
+  }
+  // Scans the first `len` elements of `data`.
+  pub(crate) fn record_raw(
+    &self,
+    wgpu: &WGPUController,
+    encoder: &mut CommandEncoder,
+    data: &wgpu::Buffer,
+    len: usize,
+  ) {
+    self.record_level(wgpu, encoder, data, len, 0);
+  }
+  fn record_level(
+    &self,
+    wgpu: &WGPUController,
+    encoder: &mut CommandEncoder,
+    data: &wgpu::Buffer,
+    len: usize,
+    level: usize,
+  ) {
+    if len == 0 {
+      return;
+    }
+    let blocks = block_count(len);
+    let block_sums = self.block_sums.get(wgpu, level, blocks);
+    let bind_group = self
+      .layout
+      .build_group(wgpu)
+      .with_buffer_binding_entry(data, 0, binding_size::<T>(len))
+      .with_buffer_binding_entry(&block_sums, 0, binding_size::<T>(blocks))
+      .build();
+    let [x, y] = workgroup_grid(wgpu, blocks);
+    encoder
+      .compute_pass()
+      .with_pipeline(&self.scan_blocks_pipeline)
+      .with_bind_group(0, &bind_group)
+      .dispatch(x, y, 1);
+    if blocks > 1 {
+      self.record_level(wgpu, encoder, &block_sums, blocks, level + 1);
+      encoder
+        .compute_pass()
+        .with_pipeline(&self.add_block_sums_pipeline)
+        .with_bind_group(0, &bind_group)
+        .dispatch(x, y, 1);
+    }
+  }
+}
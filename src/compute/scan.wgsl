@@ -0,0 +1,53 @@
+@group(0) @binding(0) var<storage, read_write> data: array<Element>;
+@group(0) @binding(1) var<storage, read_write> block_sums: array<Element>;
+
+var<workgroup> partial_sums: array<Element, WORKGROUP_SIZE>;
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn scan_blocks(
+  @builtin(local_invocation_index) local_index: u32,
+  @builtin(workgroup_id) workgroup_id: vec3u,
+  @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+  let block = workgroup_index(workgroup_id, num_workgroups);
+  let index = block * WORKGROUP_SIZE + local_index;
+  let len = arrayLength(&data);
+  var value = Element(0);
+  if index < len {
+    value = data[index];
+  }
+  partial_sums[local_index] = value;
+  workgroupBarrier();
+  for (var offset = 1u; offset < WORKGROUP_SIZE; offset <<= 1u) {
+    var addend = Element(0);
+    if local_index >= offset {
+      addend = partial_sums[local_index - offset];
+    }
+    workgroupBarrier();
+    partial_sums[local_index] += addend;
+    workgroupBarrier();
+  }
+  if index < len {
+    if local_index == 0u {
+      data[index] = Element(0);
+    } else {
+      data[index] = partial_sums[local_index - 1u];
+    }
+  }
+  if local_index == WORKGROUP_SIZE - 1u && block < arrayLength(&block_sums) {
+    block_sums[block] = partial_sums[local_index];
+  }
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn add_block_sums(
+  @builtin(local_invocation_index) local_index: u32,
+  @builtin(workgroup_id) workgroup_id: vec3u,
+  @builtin(num_workgroups) num_workgroups: vec3u,
+) {
+  let block = workgroup_index(workgroup_id, num_workgroups);
+  let index = block * WORKGROUP_SIZE + local_index;
+  if index < arrayLength(&data) {
+    data[index] += block_sums[block];
+  }
+}
@@ -0,0 +1,220 @@
+use crate::wgpu::{
+  buffer::{ArrayBuffer, GpuBuffer},
+  controller::WGPUController,
+  encoder::CommandEncoder,
+};
+use bytemuck::AnyBitPattern;
+
+use super::{ComputeElement, ExclusiveScan, RadixSort, Reduce, ReduceOp};
+
+// Shorter lengths follow longer ones so reused scratch buffers that are longer
+// than needed get covered too.
+const LENGTHS: [usize; 6] = [0, 1, 257, 256 * 256 + 1, 255, 256];
+
+// Deterministic pseudo-random u32s, so failures are reproducible.
+fn random(len: usize, seed: u32) -> Vec<u32> {
+  let mut state = seed.wrapping_mul(0x9e3779b9) | 1;
+  (0..len)
+    .map(|_| {
+      state ^= state << 13;
+      state ^= state >> 17;
+      state ^= state << 5;
+      state
+    })
+    .collect()
+}
+
+fn run<T: ComputeElement + AnyBitPattern>(
+  wgpu: &WGPUController,
+  data: &[T],
+  mut record: impl FnMut(&mut CommandEncoder, &ArrayBuffer<T>),
+) -> Vec<T> {
+  let buffer = wgpu.array_buffer(data);
+  wgpu.with_encoder(|encoder| record(encoder, &buffer));
+  buffer.read(wgpu)
+}
+
+#[test]
+fn exclusive_scan_u32() {
+  let wgpu = WGPUController::for_tests();
+  let scan = ExclusiveScan::<u32>::new(&wgpu);
+  for len in LENGTHS {
+    let data = random(len, len as u32);
+    let expected: Vec<u32> = data
+      .iter()
+      .scan(0u32, |sum, &x| {
+        let prefix = *sum;
+        *sum = sum.wrapping_add(x);
+        Some(prefix)
+      })
+      .collect();
+    let result = run(&wgpu, &data, |encoder, buffer| {
+      scan.record(&wgpu, encoder, buffer)
+    });
+    assert_eq!(result, expected, "scan of {len} u32s");
+  }
+}
+
+#[test]
+fn exclusive_scan_f32() {
+  let wgpu = WGPUController::for_tests();
+  let scan = ExclusiveScan::<f32>::new(&wgpu);
+  for len in LENGTHS {
+    // Small integers keep every partial sum exactly representable, so the
+    // result doesn't depend on the order of additions.
+    let data: Vec<f32> = random(len, len as u32)
+      .iter()
+      .map(|x| (x % 8) as f32)
+      .collect();
+    let expected: Vec<f32> = data
+      .iter()
+      .scan(0., |sum, &x| {
+        let prefix = *sum;
+        *sum += x;
+        Some(prefix)
+      })
+      .collect();
+    let result = run(&wgpu, &data, |encoder, buffer| {
+      scan.record(&wgpu, encoder, buffer)
+    });
+    assert_eq!(result, expected, "scan of {len} f32s");
+  }
+}
+
+fn check_reduce<
+  T: ComputeElement + AnyBitPattern + PartialEq + std::fmt::Debug,
+>(
+  wgpu: &WGPUController,
+  op: ReduceOp,
+  data: &[T],
+  expected: T,
+) {
+  let reduce = Reduce::<T>::new(wgpu, op);
+  let output = wgpu.array_buffer(&[T::zeroed()]);
+  let input = wgpu.array_buffer(data);
+  wgpu.with_encoder(|encoder| reduce.record(wgpu, encoder, &input, &output));
+  assert_eq!(
+    output.read(wgpu)[0],
+    expected,
+    "{op:?} of {} elements",
+    data.len()
+  );
+}
+
+#[test]
+fn reduce_u32() {
+  let wgpu = WGPUController::for_tests();
+  for len in LENGTHS.into_iter().filter(|&len| len > 0) {
+    let data = random(len, len as u32);
+    let sum = data.iter().fold(0u32, |sum, &x| sum.wrapping_add(x));
+    check_reduce(&wgpu, ReduceOp::Sum, &data, sum);
+    check_reduce(&wgpu, ReduceOp::Min, &data, *data.iter().min().unwrap());
+    check_reduce(&wgpu, ReduceOp::Max, &data, *data.iter().max().unwrap());
+  }
+}
+
+#[test]
+fn reduce_empty() {
+  let wgpu = WGPUController::for_tests();
+  let reduce = Reduce::<u32>::new(&wgpu, ReduceOp::Sum);
+  let output = wgpu.array_buffer(&[7u32]);
+  let input = wgpu.array_buffer::<u32>(&[]);
+  wgpu.with_encoder(|encoder| reduce.record(&wgpu, encoder, &input, &output));
+  assert_eq!(output.read(&wgpu), [0]);
+}
+
+#[test]
+#[should_panic(expected = "Min reduction of an empty buffer")]
+fn reduce_empty_min() {
+  let wgpu = WGPUController::for_tests();
+  let reduce = Reduce::<f32>::new(&wgpu, ReduceOp::Min);
+  let output = wgpu.array_buffer(&[0f32]);
+  let input = wgpu.array_buffer::<f32>(&[]);
+  wgpu.with_encoder(|encoder| reduce.record(&wgpu, encoder, &input, &output));
+}
+
+#[test]
+fn reduce_f32() {
+  let wgpu = WGPUController::for_tests();
+  for len in LENGTHS.into_iter().filter(|&len| len > 0) {
+    let data: Vec<f32> = random(len, len as u32)
+      .iter()
+      .map(|&x| (x % 2001) as f32 - 1000.)
+      .collect();
+    let sum = data.iter().sum();
+    let min = data.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = data.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    check_reduce(&wgpu, ReduceOp::Sum, &data, sum);
+    check_reduce(&wgpu, ReduceOp::Min, &data, min);
+    check_reduce(&wgpu, ReduceOp::Max, &data, max);
+  }
+}
+
+// Sorts `keys` on the GPU with and without their indices as values, and
+// compares against a stable CPU sort. Keys are drawn from a small range so
+// there are plenty of ties for the values to check stability with.
+fn check_radix_sort<K: ComputeElement + AnyBitPattern + std::fmt::Debug>(
+  make_key: impl Fn(u32) -> K,
+  compare: impl Fn(&K, &K) -> std::cmp::Ordering,
+) {
+  let wgpu = WGPUController::for_tests();
+  let sort = RadixSort::<K>::new(&wgpu);
+  for len in LENGTHS {
+    let keys: Vec<K> =
+      random(len, len as u32).into_iter().map(&make_key).collect();
+    let mut expected: Vec<(K, u32)> =
+      keys.iter().copied().zip(0..len as u32).collect();
+    expected.sort_by(|a, b| compare(&a.0, &b.0));
+    let expected_keys: Vec<u32> = expected
+      .iter()
+      .map(|(key, _)| bytemuck::cast(*key))
+      .collect();
+    let expected_values: Vec<u32> =
+      expected.iter().map(|&(_, value)| value).collect();
+
+    let sorted = run(&wgpu, &keys, |encoder, buffer| {
+      sort.record(&wgpu, encoder, buffer)
+    });
+    let sorted: Vec<u32> = bytemuck::cast_slice(&sorted).to_vec();
+    assert_eq!(sorted, expected_keys, "sort of {len} keys");
+
+    let key_buffer = wgpu.array_buffer(&keys);
+    let values: Vec<u32> = (0..len as u32).collect();
+    let value_buffer = wgpu.array_buffer(&values);
+    wgpu.with_encoder(|encoder| {
+      sort.record_with_values(&wgpu, encoder, &key_buffer, &value_buffer)
+    });
+    let sorted: Vec<u32> =
+      bytemuck::cast_slice(&key_buffer.read(&wgpu)).to_vec();
+    assert_eq!(sorted, expected_keys, "sort of {len} keys with values");
+    assert_eq!(
+      value_buffer.read(&wgpu),
+      expected_values,
+      "values of a sort of {len} keys aren't in stable order"
+    );
+  }
+}
+
+#[test]
+fn radix_sort_u32() {
+  check_radix_sort(|x| x % 1000 * 4_000_000, u32::cmp);
+}
+
+#[test]
+fn radix_sort_i32() {
+  check_radix_sort(|x| (x % 2001) as i32 - 1000, i32::cmp);
+}
+
+#[test]
+fn radix_sort_f32() {
+  check_radix_sort(
+    |x| match x % 64 {
+      0 => 0.,
+      1 => -0.,
+      2 => f32::INFINITY,
+      3 => f32::NEG_INFINITY,
+      _ => ((x >> 6) % 2001) as f32 * 0.25 - 250.,
+    },
+    f32::total_cmp,
+  );
+}
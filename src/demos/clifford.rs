@@ -2,7 +2,7 @@ use crate::{
   sketch::{FrameData, Sketch},
   wgpu::{
     bind::BindGroupWithLayout,
-    buffer::{ArrayBuffer, Buffer},
+    buffer::{ArrayBuffer, Buffer, GpuBuffer, Uniform, Vertex},
     controller::WGPUController,
   },
 };
@@ -19,8 +19,8 @@ pub struct CliffordSketchInner {
   uniform_bind_group: BindGroupWithLayout,
   render_points_bind_group: BindGroupWithLayout,
   compute_bind_group: BindGroupWithLayout,
-  corner_vertex_buffer: ArrayBuffer<[f32; 2]>,
-  scale_buffer: Buffer<[f32; 2]>,
+  corner_vertex_buffer: ArrayBuffer<[f32; 2], Vertex>,
+  scale_buffer: Buffer<[f32; 2], Uniform>,
   render_pipeline: RenderPipeline,
   compute_pipeline: ComputePipeline,
 }
@@ -33,16 +33,16 @@ impl CliffordSketch {
 
 impl Sketch for CliffordSketch {
   fn init(&mut self, wgpu: &WGPUController) {
-    let scale_buffer = wgpu.buffer([0., 0.]);
+    let scale_buffer = wgpu.uniform_buffer([0., 0.]);
     let mut rng = rand::rng();
-    let point_buffer = wgpu.array_buffer(
+    let point_buffer = wgpu.storage_array_buffer(
       &std::iter::repeat_with(|| {
         [rng.random::<f32>() * 2. - 1., rng.random::<f32>() * 2. - 1.]
       })
       .take(POINTS)
       .collect::<Vec<_>>(),
     );
-    let corner_vertex_buffer = wgpu.array_buffer(&[
+    let corner_vertex_buffer = wgpu.vertex_array_buffer(&[
       [1., 1.],
       [-1., -1.],
       [1., -1.],
@@ -97,13 +97,15 @@ impl Sketch for CliffordSketch {
   ) {
     if let CliffordSketch(Some(inner)) = self {
       let dim_min = data.dimensions[0].min(data.dimensions[1]) as f32;
-      wgpu.write_buffer(
-        &inner.scale_buffer,
-        [
-          dim_min / data.dimensions[0] as f32,
-          dim_min / data.dimensions[1] as f32,
-        ],
-      );
+      wgpu
+        .write_buffer(
+          &inner.scale_buffer,
+          [
+            dim_min / data.dimensions[0] as f32,
+            dim_min / data.dimensions[1] as f32,
+          ],
+        )
+        .unwrap();
       wgpu.with_encoder(|encoder| {
         encoder
           .compute_pass()
@@ -2,7 +2,7 @@ use crate::{
   sketch::{FrameData, Sketch},
   wgpu::{
     bind::BindGroupWithLayout,
-    buffer::{ArrayBuffer, Buffer},
+    buffer::{ArrayBuffer, Buffer, GpuBuffer, Index, Uniform, Vertex},
     controller::WGPUController,
   },
 };
@@ -10,10 +10,10 @@ use wgpu::{RenderPipeline, TextureView};
 
 pub struct SimpleSketchInner {
   primary_bind_group: BindGroupWithLayout,
-  corner_vertex_buffer: ArrayBuffer<[f32; 2]>,
-  corner_index_buffer: ArrayBuffer<u16>,
-  time_buffer: Buffer<f32>,
-  dimensions_buffer: Buffer<[f32; 2]>,
+  corner_vertex_buffer: ArrayBuffer<[f32; 2], Vertex>,
+  corner_index_buffer: ArrayBuffer<u16, Index>,
+  time_buffer: Buffer<f32, Uniform>,
+  dimensions_buffer: Buffer<[f32; 2], Uniform>,
   render_pipeline: RenderPipeline,
 }
 
@@ -26,11 +26,11 @@ impl SimpleSketch {
 
 impl Sketch for SimpleSketch {
   fn init(&mut self, wgpu: &WGPUController) {
-    let time_buffer = wgpu.buffer(0.);
-    let dimensions_buffer = wgpu.buffer([0., 0.]);
+    let time_buffer = wgpu.uniform_buffer(0.);
+    let dimensions_buffer = wgpu.uniform_buffer([0., 0.]);
     let corner_vertex_buffer =
-      wgpu.array_buffer(&[[-1., -1.], [1., -1.], [1., 1.], [-1., 1.]]);
-    let corner_index_buffer = wgpu.array_buffer(&[2, 0, 1, 0, 2, 3]);
+      wgpu.vertex_array_buffer(&[[-1., -1.], [1., -1.], [1., 1.], [-1., 1.]]);
+    let corner_index_buffer = wgpu.index_array_buffer(&[2, 0, 1, 0, 2, 3]);
     let primary_bind_group = wgpu
       .build_bind_group_with_layout()
       .with_uniform_buffer_entry(&dimensions_buffer)
@@ -63,7 +63,9 @@ impl Sketch for SimpleSketch {
     if let Self(Some(inner)) = self {
       wgpu
         .write_buffer(&inner.dimensions_buffer, data.dimensions)
-        .write_buffer(&inner.time_buffer, data.t);
+        .unwrap()
+        .write_buffer(&inner.time_buffer, data.t)
+        .unwrap();
       wgpu.with_encoder(|encoder| {
         encoder
           .simple_render_pass(&surface_view)
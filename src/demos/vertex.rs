@@ -4,7 +4,9 @@ use crate::{
   sketch::{FrameData, Sketch},
   wgpu::{
     bind::BindGroupWithLayout,
-    buffer::{ArrayBuffer, Buffer},
+    buffer::{
+      ArrayBuffer, Buffer, GpuBuffer, MirroredArrayBuffer, Uniform, Vertex,
+    },
     controller::WGPUController,
   },
 };
@@ -23,10 +25,9 @@ struct Circle {
 
 pub struct VertexSketchInner {
   primary_bind_group: BindGroupWithLayout,
-  corner_vertex_buffer: ArrayBuffer<[f32; 2]>,
-  circles: [Circle; CIRCLES],
-  circle_instance_buffer: ArrayBuffer<Circle>,
-  scale_buffer: Buffer<[f32; 2]>,
+  corner_vertex_buffer: ArrayBuffer<[f32; 2], Vertex>,
+  circles: MirroredArrayBuffer<Circle, Vertex>,
+  scale_buffer: Buffer<[f32; 2], Uniform>,
   render_pipeline: RenderPipeline,
 }
 
@@ -39,8 +40,8 @@ impl VertexSketch {
 
 impl Sketch for VertexSketch {
   fn init(&mut self, wgpu: &WGPUController) {
-    let scale_buffer = wgpu.buffer([0., 0.]);
-    let corner_vertex_buffer = wgpu.array_buffer(&[
+    let scale_buffer = wgpu.uniform_buffer([0., 0.]);
+    let corner_vertex_buffer = wgpu.vertex_array_buffer(&[
       [1., 1.],
       [-1., -1.],
       [1., -1.],
@@ -48,8 +49,7 @@ impl Sketch for VertexSketch {
       [1., 1.],
       [-1., 1.],
     ]);
-    let circles: [Circle; CIRCLES] = Zeroable::zeroed();
-    let circle_instance_buffer = wgpu.array_buffer(&circles);
+    let circles = wgpu.mirrored_array_buffer(vec![Circle::zeroed(); CIRCLES]);
     let primary_bind_group = wgpu
       .build_bind_group_with_layout()
       .with_uniform_buffer_entry(&scale_buffer)
@@ -61,7 +61,7 @@ impl Sketch for VertexSketch {
         corner_vertex_buffer
           .vertex_layout(&wgpu::vertex_attr_array![0 => Float32x2]),
       )
-      .add_vertex_buffer_layout(circle_instance_buffer.instance_layout(
+      .add_vertex_buffer_layout(circles.buffer().instance_layout(
         &wgpu::vertex_attr_array![1 => Float32, 2 => Float32, 3 => Float32],
       ))
       .build_with_shader(&wgpu.shader(wgpu::include_wgsl!("vertex.wgsl")));
@@ -70,7 +70,6 @@ impl Sketch for VertexSketch {
       scale_buffer,
       primary_bind_group,
       corner_vertex_buffer,
-      circle_instance_buffer,
       render_pipeline,
     });
   }
@@ -101,13 +100,14 @@ impl Sketch for VertexSketch {
             dim_min / data.dimensions[1] as f32,
           ],
         )
-        .write_array_buffer(&inner.circle_instance_buffer, &inner.circles);
+        .unwrap();
       wgpu.with_encoder(|encoder| {
+        inner.circles.flush_with_encoder(encoder, wgpu).unwrap();
         encoder
           .simple_render_pass(&surface_view)
           .with_bind_groups([&inner.primary_bind_group])
           .with_vertex_buffer(0, &inner.corner_vertex_buffer)
-          .with_vertex_buffer(1, &inner.circle_instance_buffer)
+          .with_vertex_buffer(1, &inner.circles)
           .with_pipeline(&inner.render_pipeline)
           .draw(0..6, 0..CIRCLES as u32);
       })
@@ -1,6 +1,7 @@
 #[macro_use]
 
 pub mod app;
+pub mod compute;
 pub mod demos;
 pub mod macros;
 pub mod sketch;
@@ -3,7 +3,10 @@ use std::collections::HashSet;
 use wgpu::{Features, TextureView};
 use winit::keyboard::SmolStr;
 
-use crate::{app::run_sketch, wgpu::controller::WGPUController};
+use crate::{
+  app::run_sketch,
+  wgpu::{controller::WGPUController, hdr::HdrSettings},
+};
 
 pub struct FrameData {
   pub t: f32,
@@ -33,6 +36,11 @@ pub trait Sketch: Sized {
   fn required_features() -> Features {
     Features::empty()
   }
+  // Opts into HDR rendering, where `update` gets a view of an Rgba16Float
+  // scene target that's tonemapped onto the surface afterwards.
+  fn hdr() -> Option<HdrSettings> {
+    None
+  }
   fn run(self) {
     pollster::block_on(run_sketch(self));
   }
@@ -0,0 +1,105 @@
+use std::{cell::Cell, marker::PhantomData, num::NonZero};
+
+use bytemuck::NoUninit;
+use wgpu::{BindGroup, BufferUsages, DynamicOffset};
+
+use super::{
+  bind::{BindGroupLayout, BindGroupWithLayout},
+  buffer::{RawBuffer, Uniform},
+  controller::WGPUController,
+  registry::ResourceToken,
+};
+
+// Suballocates uniform blocks of type `T` out of a single buffer, handing out
+// dynamic offsets into one shared bind group. Allocations are reset at the
+// start of every frame, so each block is only valid for the frame in which it
+// was pushed.
+pub struct UniformArena<T: NoUninit> {
+  _phantom: PhantomData<T>,
+  buffer: wgpu::Buffer,
+  bind_group: BindGroupWithLayout,
+  stride: u64,
+  capacity: usize,
+  cursor: Cell<usize>,
+  frame_index: Cell<usize>,
+  _token: Option<ResourceToken>,
+}
+
+impl<T: NoUninit> UniformArena<T> {
+  #[track_caller]
+  pub fn new(wgpu: &WGPUController, capacity: usize) -> Self {
+    let block_size = NonZero::new(std::mem::size_of::<T>() as u64)
+      .expect("UniformArena can't hold zero-sized uniform blocks");
+    let alignment =
+      wgpu.device.limits().min_uniform_buffer_offset_alignment as u64;
+    let stride = block_size.get().next_multiple_of(alignment);
+    let buffer = wgpu.device.create_buffer(&wgpu::BufferDescriptor {
+      label: Some("uniform arena"),
+      size: stride * capacity.max(1) as u64,
+      usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+      mapped_at_creation: false,
+    });
+    let bind_group = wgpu
+      .build_bind_group_with_layout()
+      .with_dynamic_uniform_buffer_entry(
+        RawBuffer::<Uniform>::new(&buffer),
+        block_size,
+      )
+      .build();
+    Self {
+      _phantom: PhantomData,
+      bind_group,
+      stride,
+      capacity,
+      cursor: Cell::new(0),
+      frame_index: Cell::new(wgpu.frame_index()),
+      _token: wgpu.register_buffer(
+        Some("uniform arena"),
+        buffer.size(),
+        buffer.usage(),
+      ),
+      buffer,
+    }
+  }
+  pub fn layout(&self) -> &BindGroupLayout {
+    &self.bind_group.layout
+  }
+  pub fn bind_group(&self) -> &BindGroup {
+    &self.bind_group.group
+  }
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+  pub fn len(&self) -> usize {
+    self.cursor.get()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+  pub fn reset(&self) {
+    self.cursor.set(0);
+  }
+  pub fn push(
+    &self,
+    wgpu: &WGPUController,
+    value: T,
+  ) -> (&BindGroup, DynamicOffset) {
+    if self.frame_index.get() != wgpu.frame_index() {
+      self.frame_index.set(wgpu.frame_index());
+      self.reset();
+    }
+    let index = self.cursor.get();
+    assert!(
+      index < self.capacity,
+      "UniformArena is full ({} blocks were pushed this frame), create it \
+      with a larger capacity",
+      self.capacity
+    );
+    self.cursor.set(index + 1);
+    let offset = index as u64 * self.stride;
+    wgpu
+      .queue
+      .write_buffer(&self.buffer, offset, bytemuck::bytes_of(&value));
+    (&self.bind_group.group, offset as DynamicOffset)
+  }
+}
@@ -0,0 +1,429 @@
+use std::fmt::Display;
+
+use ::image::DynamicImage;
+use bytemuck::{NoUninit, Zeroable};
+use wgpu::{
+  Extent3d, Origin3d, TexelCopyBufferLayout, TexelCopyTextureInfo,
+  TextureAspect, TextureFormat, TextureView, TextureViewDimension,
+  VertexAttribute, VertexFormat,
+};
+
+use super::{
+  controller::WGPUController,
+  image::ColorSpace,
+  texture::{Texture, TextureBuilder, TextureViewSource},
+};
+
+// The normalized texture coordinates of an atlas entry and the array layer
+// it's on, laid out so it can be used as instance data directly.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, NoUninit, Zeroable)]
+pub struct AtlasRect {
+  pub min: [f32; 2],
+  pub max: [f32; 2],
+  pub page: u32,
+}
+
+impl AtlasRect {
+  // Instance attributes for `min`, `max` and `page`, starting at
+  // `first_location` so they can follow per-vertex attributes.
+  pub fn attributes(first_location: u32) -> [VertexAttribute; 3] {
+    let formats = [
+      VertexFormat::Float32x2,
+      VertexFormat::Float32x2,
+      VertexFormat::Uint32,
+    ];
+    let mut offset = 0;
+    std::array::from_fn(|i| {
+      let attribute = VertexAttribute {
+        format: formats[i],
+        offset,
+        shader_location: first_location + i as u32,
+      };
+      offset += formats[i].size();
+      attribute
+    })
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AtlasId(usize);
+
+// Where an entry is in the atlas, in texels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasRegion {
+  pub page: u32,
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtlasError {
+  TooLarge {
+    width: u32,
+    height: u32,
+    page_size: u32,
+  },
+  OutOfPages(u32),
+}
+
+impl Display for AtlasError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AtlasError::TooLarge {
+        width,
+        height,
+        page_size,
+      } => write!(
+        f,
+        "{width}x{height} region doesn't fit in a {page_size}x{page_size} \
+        atlas page"
+      ),
+      AtlasError::OutOfPages(pages) => {
+        write!(
+          f,
+          "atlas is full and already has the maximum of {pages} pages"
+        )
+      }
+    }
+  }
+}
+
+impl std::error::Error for AtlasError {}
+
+#[derive(Debug)]
+struct Shelf {
+  y: u32,
+  height: u32,
+  width_used: u32,
+}
+
+// Packs rectangles into rows of fixed height, placing each in the shortest
+// shelf it fits in and opening a new shelf below the rest otherwise.
+#[derive(Debug)]
+struct ShelfPacker {
+  size: u32,
+  shelves: Vec<Shelf>,
+  height_used: u32,
+}
+
+impl ShelfPacker {
+  fn new(size: u32) -> Self {
+    Self {
+      size,
+      shelves: vec![],
+      height_used: 0,
+    }
+  }
+  fn pack(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+    let size = self.size;
+    let shelf = self
+      .shelves
+      .iter_mut()
+      .filter(|shelf| {
+        shelf.height >= height && shelf.width_used + width <= size
+      })
+      .min_by_key(|shelf| shelf.height);
+    if let Some(shelf) = shelf {
+      let x = shelf.width_used;
+      shelf.width_used += width;
+      return Some((x, shelf.y));
+    }
+    if self.height_used + height > size {
+      return None;
+    }
+    let y = self.height_used;
+    self.height_used += height;
+    self.shelves.push(Shelf {
+      y,
+      height,
+      width_used: width,
+    });
+    Some((0, y))
+  }
+}
+
+pub struct TextureAtlasBuilder<'s, 'w, 'window> {
+  wgpu: &'w WGPUController<'window>,
+  label: Option<&'s str>,
+  page_size: u32,
+  padding: u32,
+  color_space: ColorSpace,
+  max_pages: u32,
+}
+
+impl<'s, 'w, 'window> TextureAtlasBuilder<'s, 'w, 'window> {
+  pub fn new(wgpu: &'w WGPUController<'window>, page_size: u32) -> Self {
+    Self {
+      wgpu,
+      label: None,
+      page_size,
+      padding: 1,
+      color_space: ColorSpace::Srgb,
+      max_pages: wgpu.device.limits().max_texture_array_layers,
+    }
+  }
+  pub fn with_label(mut self, label: &'s str) -> Self {
+    self.label = Some(label);
+    self
+  }
+  // Empty texels left between entries and around the edges of each page, so
+  // filtering doesn't bleed between neighbours or wrap around.
+  pub fn with_padding(mut self, padding: u32) -> Self {
+    self.padding = padding;
+    self
+  }
+  pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+    self.color_space = color_space;
+    self
+  }
+  pub fn with_max_pages(mut self, max_pages: u32) -> Self {
+    self.max_pages =
+      max_pages.min(self.wgpu.device.limits().max_texture_array_layers);
+    self
+  }
+  pub fn build(self) -> TextureAtlas {
+    let format = match self.color_space {
+      ColorSpace::Srgb => TextureFormat::Rgba8UnormSrgb,
+      ColorSpace::Linear => TextureFormat::Rgba8Unorm,
+    };
+    TextureAtlas {
+      texture: build_pages(self.wgpu, self.label, self.page_size, format, 1),
+      label: self.label.map(str::to_owned),
+      page_size: self.page_size,
+      padding: self.padding,
+      format,
+      max_pages: self.max_pages,
+      pages: vec![],
+      regions: vec![],
+      generation: 0,
+    }
+  }
+}
+
+fn build_pages(
+  wgpu: &WGPUController,
+  label: Option<&str>,
+  page_size: u32,
+  format: TextureFormat,
+  pages: u32,
+) -> Texture {
+  let mut builder =
+    TextureBuilder::new_2d_array(wgpu, page_size, page_size, pages)
+      .with_format(format)
+      .with_usage(
+        wgpu::TextureUsages::TEXTURE_BINDING
+          | wgpu::TextureUsages::COPY_SRC
+          | wgpu::TextureUsages::COPY_DST,
+      );
+  if let Some(label) = label {
+    builder = builder.with_label(label);
+  }
+  builder.build()
+}
+
+// RGBA images packed into the layers of a 2D array texture. Pages are added
+// as earlier ones fill up, and the texture is recreated with room for more
+// when it runs out of layers, which bumps `generation` so bind groups using it
+// can be rebuilt.
+pub struct TextureAtlas {
+  texture: Texture,
+  label: Option<String>,
+  page_size: u32,
+  padding: u32,
+  format: TextureFormat,
+  max_pages: u32,
+  pages: Vec<ShelfPacker>,
+  regions: Vec<AtlasRegion>,
+  generation: usize,
+}
+
+impl TextureAtlas {
+  pub fn texture(&self) -> &Texture {
+    &self.texture
+  }
+  pub fn view(&self) -> &TextureView {
+    self.texture.view()
+  }
+  pub fn page_size(&self) -> u32 {
+    self.page_size
+  }
+  pub fn page_count(&self) -> u32 {
+    self.pages.len() as u32
+  }
+  pub fn len(&self) -> usize {
+    self.regions.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.regions.is_empty()
+  }
+  pub fn generation(&self) -> usize {
+    self.generation
+  }
+  pub fn region(&self, id: AtlasId) -> AtlasRegion {
+    self.regions[id.0]
+  }
+  pub fn rect(&self, id: AtlasId) -> AtlasRect {
+    let region = self.region(id);
+    let size = self.page_size as f32;
+    AtlasRect {
+      min: [region.x as f32 / size, region.y as f32 / size],
+      max: [
+        (region.x + region.width) as f32 / size,
+        (region.y + region.height) as f32 / size,
+      ],
+      page: region.page,
+    }
+  }
+  pub fn rects(&self) -> Vec<AtlasRect> {
+    (0..self.regions.len())
+      .map(|i| self.rect(AtlasId(i)))
+      .collect()
+  }
+  pub fn add_image(
+    &mut self,
+    wgpu: &WGPUController,
+    image: &DynamicImage,
+  ) -> Result<AtlasId, AtlasError> {
+    let image = image.to_rgba8();
+    self.add_rgba(wgpu, image.width(), image.height(), &image)
+  }
+  // Adds a region of tightly packed RGBA8 texels, such as one generated at
+  // runtime.
+  #[track_caller]
+  pub fn add_rgba(
+    &mut self,
+    wgpu: &WGPUController,
+    width: u32,
+    height: u32,
+    data: &[u8],
+  ) -> Result<AtlasId, AtlasError> {
+    assert_eq!(
+      data.len(),
+      width as usize * height as usize * 4,
+      "atlas data doesn't match a {width}x{height} RGBA region"
+    );
+    let region = self.allocate(wgpu, width, height)?;
+    if width > 0 && height > 0 {
+      wgpu.queue.write_texture(
+        TexelCopyTextureInfo {
+          texture: &self.texture,
+          mip_level: 0,
+          origin: Origin3d {
+            x: region.x,
+            y: region.y,
+            z: region.page,
+          },
+          aspect: TextureAspect::All,
+        },
+        data,
+        TexelCopyBufferLayout {
+          offset: 0,
+          bytes_per_row: Some(width * 4),
+          rows_per_image: None,
+        },
+        Extent3d {
+          width,
+          height,
+          depth_or_array_layers: 1,
+        },
+      );
+    }
+    self.regions.push(region);
+    Ok(AtlasId(self.regions.len() - 1))
+  }
+  // Entries are packed inside a margin of `padding` on the top and left of
+  // each page, and padded on their own right and bottom, so every entry ends
+  // up with `padding` empty texels on all sides.
+  fn packed_size(&self) -> u32 {
+    self.page_size.saturating_sub(self.padding)
+  }
+  fn allocate(
+    &mut self,
+    wgpu: &WGPUController,
+    width: u32,
+    height: u32,
+  ) -> Result<AtlasRegion, AtlasError> {
+    let (padded_width, padded_height) = (
+      width.saturating_add(self.padding),
+      height.saturating_add(self.padding),
+    );
+    let packed_size = self.packed_size();
+    if padded_width > packed_size || padded_height > packed_size {
+      return Err(AtlasError::TooLarge {
+        width,
+        height,
+        page_size: self.page_size,
+      });
+    }
+    let packed =
+      self
+        .pages
+        .iter_mut()
+        .enumerate()
+        .find_map(|(page, packer)| {
+          packer
+            .pack(padded_width, padded_height)
+            .map(|(x, y)| (page as u32, x, y))
+        });
+    let (page, x, y) = match packed {
+      Some(packed) => packed,
+      None => {
+        let page = self.add_page(wgpu)?;
+        let (x, y) = self.pages[page as usize]
+          .pack(padded_width, padded_height)
+          .expect("region should fit in an empty atlas page");
+        (page, x, y)
+      }
+    };
+    Ok(AtlasRegion {
+      page,
+      x: x + self.padding,
+      y: y + self.padding,
+      width,
+      height,
+    })
+  }
+  fn add_page(&mut self, wgpu: &WGPUController) -> Result<u32, AtlasError> {
+    let page = self.pages.len() as u32;
+    if page == self.max_pages {
+      return Err(AtlasError::OutOfPages(self.max_pages));
+    }
+    let layers = self.texture.depth_or_array_layers();
+    if page == layers {
+      self.grow(wgpu, (layers * 2).min(self.max_pages));
+    }
+    self.pages.push(ShelfPacker::new(self.packed_size()));
+    Ok(page)
+  }
+  // Recreates the texture with `layers` layers, copying the existing pages.
+  fn grow(&mut self, wgpu: &WGPUController, layers: u32) {
+    let texture = build_pages(
+      wgpu,
+      self.label.as_deref(),
+      self.page_size,
+      self.format,
+      layers,
+    );
+    wgpu.submit_without_belt(|encoder| {
+      encoder.copy_texture_to_texture(
+        self.texture.as_image_copy(),
+        texture.as_image_copy(),
+        self.texture.size(),
+      );
+    });
+    self.texture = texture;
+    self.generation += 1;
+  }
+}
+
+impl<'v> TextureViewSource<'v> for &'v TextureAtlas {
+  fn texture_view(self) -> &'v TextureView {
+    self.texture.view()
+  }
+  fn view_dimension(&self) -> TextureViewDimension {
+    TextureViewDimension::D2Array
+  }
+}
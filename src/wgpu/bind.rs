@@ -2,12 +2,58 @@ use std::{num::NonZero, ops::Deref};
 
 use wgpu::{
   BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
-  BindGroupLayoutEntry, BindingResource, BindingType, Sampler,
-  SamplerBindingType, ShaderStages, TextureSampleType, TextureView,
-  TextureViewDimension,
+  BindGroupLayoutEntry, BindingResource, BindingType, BufferBinding, Features,
+  Sampler, SamplerBindingType, ShaderStages, StorageTextureAccess,
+  TextureFormatFeatureFlags, TextureSampleType, TextureUsages, TextureView,
 };
 
-use super::controller::WGPUController;
+use bytemuck::NoUninit;
+
+use super::{
+  buffer::{
+    AppendBuffer, ArrayBufferRange, BufferUsage, HasBufferUsage, Storage,
+    Supports, Uniform,
+  },
+  controller::WGPUController,
+  texture::TextureViewSource,
+};
+
+#[track_caller]
+fn check_storage_access(
+  wgpu: &WGPUController,
+  texture: &wgpu::Texture,
+  access: StorageTextureAccess,
+) {
+  assert!(
+    texture.usage().contains(TextureUsages::STORAGE_BINDING),
+    "textures need STORAGE_BINDING usage to be bound as storage textures"
+  );
+  let features = wgpu.device.features();
+  if features.contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+    return;
+  }
+  let required = match access {
+    StorageTextureAccess::WriteOnly => {
+      TextureFormatFeatureFlags::STORAGE_WRITE_ONLY
+    }
+    StorageTextureAccess::ReadOnly => {
+      TextureFormatFeatureFlags::STORAGE_READ_ONLY
+    }
+    StorageTextureAccess::ReadWrite => {
+      TextureFormatFeatureFlags::STORAGE_READ_WRITE
+    }
+    StorageTextureAccess::Atomic => TextureFormatFeatureFlags::STORAGE_ATOMIC,
+  };
+  assert!(
+    texture
+      .format()
+      .guaranteed_format_features(features)
+      .flags
+      .contains(required),
+    "{:?} textures don't support {access:?} storage access",
+    texture.format()
+  );
+}
 
 #[derive(Default)]
 pub struct BindGroupLayoutEntryBuilder {
@@ -100,6 +146,15 @@ impl<'s, 'w, 'window> BindGroupLayoutBuilder<'s, 'w, 'window> {
   pub fn with_uniform_entry(self) -> Self {
     self.with_entry(BindGroupLayoutEntryBuilder::new())
   }
+  pub fn with_dynamic_uniform_entry(self, block_size: NonZero<u64>) -> Self {
+    self.with_entry(BindGroupLayoutEntryBuilder::new().with_ty(
+      wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Uniform,
+        has_dynamic_offset: true,
+        min_binding_size: Some(block_size),
+      },
+    ))
+  }
   pub fn with_read_only_storage_entry(self) -> Self {
     self.with_entry(BindGroupLayoutEntryBuilder::new().with_ty(
       wgpu::BindingType::Buffer {
@@ -164,13 +219,59 @@ impl<'l, 's, 'a, 'w, 'window> BindGroupBuilder<'l, 's, 'a, 'w, 'window> {
     });
     self
   }
+  pub fn with_buffer_binding_entry<'b: 'a, B: Into<&'b wgpu::Buffer>>(
+    mut self,
+    buffer: B,
+    offset: u64,
+    size: Option<NonZero<u64>>,
+  ) -> Self {
+    self.entries.push(BindGroupEntry {
+      binding: self.entries.len() as u32,
+      resource: BindingResource::Buffer(BufferBinding {
+        buffer: buffer.into(),
+        offset,
+        size,
+      }),
+    });
+    self
+  }
   pub fn with_texture_entry<'b: 'a>(
     mut self,
-    texture_view: &'b TextureView,
+    texture_view: impl Into<&'b TextureView>,
+  ) -> Self {
+    self.entries.push(BindGroupEntry {
+      binding: self.entries.len() as u32,
+      resource: BindingResource::TextureView(texture_view.into()),
+    });
+    self
+  }
+  // The range has to be aligned for every way its buffer can be bound, since
+  // the layout entry it's used with isn't known here.
+  #[track_caller]
+  pub fn with_buffer_range_entry<T: NoUninit, U: BufferUsage>(
+    self,
+    range: ArrayBufferRange<'a, T, U>,
+  ) -> Self {
+    let limits = self.wgpu.device.limits();
+    let usage = range.buffer().usage();
+    let mut alignment = 1;
+    if usage.contains(wgpu::BufferUsages::UNIFORM) {
+      alignment = alignment.max(limits.min_uniform_buffer_offset_alignment);
+    }
+    if usage.contains(wgpu::BufferUsages::STORAGE) {
+      alignment = alignment.max(limits.min_storage_buffer_offset_alignment);
+    }
+    self.with_aligned_buffer_range_entry(range, alignment)
+  }
+  #[track_caller]
+  pub(crate) fn with_aligned_buffer_range_entry<T: NoUninit, U: BufferUsage>(
+    mut self,
+    range: ArrayBufferRange<'a, T, U>,
+    offset_alignment: u32,
   ) -> Self {
     self.entries.push(BindGroupEntry {
       binding: self.entries.len() as u32,
-      resource: BindingResource::TextureView(texture_view),
+      resource: BindingResource::Buffer(range.binding(offset_alignment)),
     });
     self
   }
@@ -276,42 +377,95 @@ impl<'s, 'l, 'a, 'w, 'window>
     self
   }
   pub fn with_texture_entry<'b: 'a>(
+    self,
+    texture_view: impl TextureViewSource<'b>,
+  ) -> Self {
+    let sample_type = texture_view
+      .texture_view()
+      .texture()
+      .format()
+      .sample_type(None, None)
+      .unwrap_or(TextureSampleType::Float { filterable: false });
+    self.with_texture_entry_of_sample_type(texture_view, sample_type)
+  }
+  pub fn with_texture_entry_of_sample_type<'b: 'a>(
     mut self,
-    texture_view: &'b TextureView,
+    texture_view: impl TextureViewSource<'b>,
+    sample_type: TextureSampleType,
   ) -> Self {
+    let view_dimension = texture_view.view_dimension();
+    let texture_view = texture_view.texture_view();
+    let texture = texture_view.texture();
+    let multisampled = texture.sample_count() > 1;
     self.layout_builder = self.layout_builder.with_entry(
       BindGroupLayoutEntryBuilder::new()
         .with_visibility(ShaderStages::all())
         .with_ty(BindingType::Texture {
-          sample_type: texture_view
-            .texture()
-            .format()
-            .sample_type(None, None)
-            .unwrap_or(TextureSampleType::Float { filterable: false }),
-          view_dimension: TextureViewDimension::D2,
-          multisampled: false,
+          // Multisampled textures can't be filtered.
+          sample_type: match sample_type {
+            TextureSampleType::Float { .. } if multisampled => {
+              TextureSampleType::Float { filterable: false }
+            }
+            sample_type => sample_type,
+          },
+          view_dimension,
+          multisampled,
         }),
     );
     self.group_builder = self.group_builder.with_texture_entry(texture_view);
     self
   }
-  pub fn with_texture_entry_of_sample_type<'b: 'a>(
+  // Storage textures are only visible to compute shaders unless they're read
+  // only, like writable storage buffers.
+  #[track_caller]
+  pub fn with_storage_texture_entry<'b: 'a>(
     mut self,
-    texture_view: &'b TextureView,
-    sample_type: TextureSampleType,
+    texture_view: impl TextureViewSource<'b>,
+    access: StorageTextureAccess,
   ) -> Self {
+    let view_dimension = texture_view.view_dimension();
+    let texture_view = texture_view.texture_view();
+    let texture = texture_view.texture();
+    check_storage_access(self.layout_builder.wgpu, texture, access);
     self.layout_builder = self.layout_builder.with_entry(
       BindGroupLayoutEntryBuilder::new()
-        .with_visibility(ShaderStages::all())
-        .with_ty(BindingType::Texture {
-          sample_type,
-          view_dimension: TextureViewDimension::D2,
-          multisampled: false,
+        .with_visibility(match access {
+          StorageTextureAccess::ReadOnly => ShaderStages::all(),
+          _ => ShaderStages::COMPUTE,
+        })
+        .with_ty(BindingType::StorageTexture {
+          access,
+          format: texture.format(),
+          view_dimension,
         }),
     );
     self.group_builder = self.group_builder.with_texture_entry(texture_view);
     self
   }
+  #[track_caller]
+  pub fn with_write_only_storage_texture_entry<'b: 'a>(
+    self,
+    texture_view: impl TextureViewSource<'b>,
+  ) -> Self {
+    self
+      .with_storage_texture_entry(texture_view, StorageTextureAccess::WriteOnly)
+  }
+  #[track_caller]
+  pub fn with_read_only_storage_texture_entry<'b: 'a>(
+    self,
+    texture_view: impl TextureViewSource<'b>,
+  ) -> Self {
+    self
+      .with_storage_texture_entry(texture_view, StorageTextureAccess::ReadOnly)
+  }
+  #[track_caller]
+  pub fn with_read_write_storage_texture_entry<'b: 'a>(
+    self,
+    texture_view: impl TextureViewSource<'b>,
+  ) -> Self {
+    self
+      .with_storage_texture_entry(texture_view, StorageTextureAccess::ReadWrite)
+  }
   pub fn with_sampler_entry<'b: 'a>(
     mut self,
     sampler: &'b Sampler,
@@ -327,7 +481,10 @@ impl<'s, 'l, 'a, 'w, 'window>
     self.group_builder = self.group_builder.with_sampler_entry(sampler);
     self
   }
-  pub fn with_uniform_buffer_entry<'b: 'a, B: Into<&'b wgpu::Buffer>>(
+  pub fn with_uniform_buffer_entry<
+    'b: 'a,
+    B: Into<&'b wgpu::Buffer> + HasBufferUsage<Usage: Supports<Uniform>>,
+  >(
     mut self,
     buffer: B,
   ) -> Self {
@@ -335,9 +492,83 @@ impl<'s, 'l, 'a, 'w, 'window>
     self.group_builder = self.group_builder.with_buffer_entry(buffer);
     self
   }
+  pub fn with_dynamic_uniform_buffer_entry<
+    'b: 'a,
+    B: Into<&'b wgpu::Buffer> + HasBufferUsage<Usage: Supports<Uniform>>,
+  >(
+    mut self,
+    buffer: B,
+    block_size: NonZero<u64>,
+  ) -> Self {
+    self.layout_builder =
+      self.layout_builder.with_dynamic_uniform_entry(block_size);
+    self.group_builder =
+      self
+        .group_builder
+        .with_buffer_binding_entry(buffer, 0, Some(block_size));
+    self
+  }
+  #[track_caller]
+  pub fn with_uniform_buffer_range_entry<T: NoUninit, U: Supports<Uniform>>(
+    mut self,
+    range: ArrayBufferRange<'a, T, U>,
+  ) -> Self {
+    let alignment = self
+      .layout_builder
+      .wgpu
+      .device
+      .limits()
+      .min_uniform_buffer_offset_alignment;
+    self.layout_builder = self.layout_builder.with_uniform_entry();
+    self.group_builder = self
+      .group_builder
+      .with_aligned_buffer_range_entry(range, alignment);
+    self
+  }
+  #[track_caller]
+  pub fn with_read_only_storage_buffer_range_entry<
+    T: NoUninit,
+    U: Supports<Storage>,
+  >(
+    mut self,
+    range: ArrayBufferRange<'a, T, U>,
+  ) -> Self {
+    let alignment = self
+      .layout_builder
+      .wgpu
+      .device
+      .limits()
+      .min_storage_buffer_offset_alignment;
+    self.layout_builder = self.layout_builder.with_read_only_storage_entry();
+    self.group_builder = self
+      .group_builder
+      .with_aligned_buffer_range_entry(range, alignment);
+    self
+  }
+  #[track_caller]
+  pub fn with_compute_writable_storage_buffer_range_entry<
+    T: NoUninit,
+    U: Supports<Storage>,
+  >(
+    mut self,
+    range: ArrayBufferRange<'a, T, U>,
+  ) -> Self {
+    let alignment = self
+      .layout_builder
+      .wgpu
+      .device
+      .limits()
+      .min_storage_buffer_offset_alignment;
+    self.layout_builder =
+      self.layout_builder.with_compute_writable_storage_entry();
+    self.group_builder = self
+      .group_builder
+      .with_aligned_buffer_range_entry(range, alignment);
+    self
+  }
   pub fn with_read_only_storage_buffer_entry<
     'b: 'a,
-    B: Into<&'b wgpu::Buffer>,
+    B: Into<&'b wgpu::Buffer> + HasBufferUsage<Usage: Supports<Storage>>,
   >(
     mut self,
     buffer: B,
@@ -348,7 +579,7 @@ impl<'s, 'l, 'a, 'w, 'window>
   }
   pub fn with_compute_writable_storage_buffer_entry<
     'b: 'a,
-    B: Into<&'b wgpu::Buffer>,
+    B: Into<&'b wgpu::Buffer> + HasBufferUsage<Usage: Supports<Storage>>,
   >(
     mut self,
     buffer: B,
@@ -358,9 +589,20 @@ impl<'s, 'l, 'a, 'w, 'window>
     self.group_builder = self.group_builder.with_buffer_entry(buffer);
     self
   }
+  pub fn with_append_buffer_entries<T: NoUninit, U: Supports<Storage>>(
+    self,
+    buffer: &'a AppendBuffer<T, U>,
+  ) -> Self {
+    self
+      .with_compute_writable_storage_buffer_entry(buffer.items())
+      .with_compute_writable_storage_buffer_entry(buffer.args())
+  }
   pub fn build(self) -> BindGroupWithLayout {
     let layout = self.layout_builder.build();
     let group = self.group_builder.with_layout(&layout).build();
     BindGroupWithLayout::new(layout, group)
   }
+  pub fn build_group_with_layout(self, layout: &BindGroupLayout) -> BindGroup {
+    self.group_builder.with_layout(layout).build()
+  }
 }
@@ -0,0 +1,114 @@
+use std::mem::offset_of;
+
+use bytemuck::NoUninit;
+use wgpu::BufferSlice;
+
+use crate::wgpu::{controller::WGPUController, encoder::CommandEncoder};
+
+use super::{
+  array::{ArrayBuffer, ArrayBufferBuilder},
+  data::IntoVertexBufferData,
+  gpu::GpuBuffer,
+  indirect::{DrawIndirectArgs, DrawIndirectBuffer},
+  read::read_buffer,
+  usage::{Storage, Supports, Vertex},
+};
+
+// A storage buffer that compute shaders append items to, paired with an atomic
+// counter. The counter lives in the `instance_count` of a `DrawIndirectArgs`,
+// so the appended items can be drawn as instances without a CPU round trip.
+pub struct AppendBuffer<T: NoUninit, U: Supports<Storage> = (Storage, Vertex)> {
+  items: ArrayBuffer<T, U>,
+  args: DrawIndirectBuffer,
+}
+
+impl<T: NoUninit, U: Supports<Storage>> AppendBuffer<T, U> {
+  #[track_caller]
+  pub fn new(
+    wgpu: &WGPUController,
+    capacity: usize,
+    vertex_count: u32,
+  ) -> Self {
+    Self {
+      items: ArrayBufferBuilder::empty(wgpu, capacity)
+        .with_label("append buffer items")
+        .with_usage_type()
+        .build(),
+      args: ArrayBufferBuilder::from_contents(
+        wgpu,
+        &[DrawIndirectArgs {
+          vertex_count,
+          ..Default::default()
+        }],
+      )
+      .with_label("append buffer counter")
+      .with_usage_type()
+      .build(),
+    }
+  }
+  pub fn items(&self) -> &ArrayBuffer<T, U> {
+    &self.items
+  }
+  pub fn args(&self) -> &DrawIndirectBuffer {
+    &self.args
+  }
+  pub fn capacity(&self) -> usize {
+    self.items.len()
+  }
+  pub fn reset(&self, encoder: &mut CommandEncoder) {
+    encoder.clear_buffer(
+      &self.args,
+      offset_of!(DrawIndirectArgs, instance_count) as u64,
+      Some(std::mem::size_of::<u32>() as u64),
+    );
+  }
+  // Blocks until the GPU has caught up, so this is meant for occasional CPU
+  // logic rather than every frame.
+  pub fn read_count(&self, wgpu: &WGPUController) -> u32 {
+    let args: Vec<DrawIndirectArgs> = read_buffer(wgpu, &self.args, 0, 1);
+    args[0].instance_count.min(self.capacity() as u32)
+  }
+  // Declares the bindings matching `with_append_buffer_entries`, along with a
+  // `<name>_append(item) -> bool` function that returns false once the buffer
+  // is full.
+  pub fn wgsl(
+    name: &str,
+    element_type: &str,
+    group: u32,
+    binding: u32,
+  ) -> String {
+    format!(
+      "struct {name}_counter_args {{
+  vertex_count: u32,
+  count: atomic<u32>,
+  first_vertex: u32,
+  first_instance: u32,
+}}
+
+@group({group}) @binding({binding})
+var<storage, read_write> {name}: array<{element_type}>;
+@group({group}) @binding({counter_binding})
+var<storage, read_write> {name}_counter: {name}_counter_args;
+
+fn {name}_append(item: {element_type}) -> bool {{
+  let index = atomicAdd(&{name}_counter.count, 1u);
+  if index >= arrayLength(&{name}) {{
+    atomicSub(&{name}_counter.count, 1u);
+    return false;
+  }}
+  {name}[index] = item;
+  return true;
+}}
+",
+      counter_binding = binding + 1
+    )
+  }
+}
+
+impl<'s, T: NoUninit, U: Supports<Storage> + Supports<Vertex>>
+  IntoVertexBufferData<'s> for &'s AppendBuffer<T, U>
+{
+  fn into_vertex_buffer_data(self) -> BufferSlice<'s> {
+    self.items.into_vertex_buffer_data()
+  }
+}
@@ -1,111 +1,104 @@
-use std::{marker::PhantomData, ops::Deref};
+use std::{
+  marker::PhantomData,
+  ops::{Deref, RangeBounds},
+};
 
 use bytemuck::NoUninit;
-use wgpu::{
-  util::DeviceExt, BufferSlice, BufferUsages, VertexAttribute,
-  VertexBufferLayout,
-};
+use wgpu::{util::DeviceExt, BufferUsages, COPY_BUFFER_ALIGNMENT};
 
-use crate::wgpu::controller::WGPUController;
+use crate::wgpu::{
+  controller::WGPUController, encoder::CommandEncoder, registry::ResourceToken,
+};
 
-use super::data::IntoVertexBufferData;
+use super::{
+  gpu::GpuBuffer,
+  range::ArrayBufferRange,
+  usage::{AllUsages, BufferUsage},
+  write::{check_bounds, BufferWriteError},
+};
 
 #[derive(Debug, Clone)]
-pub struct ArrayBuffer<T: NoUninit> {
-  _phantom: PhantomData<T>,
+pub struct ArrayBuffer<T: NoUninit, U: BufferUsage = AllUsages> {
+  _phantom: PhantomData<(T, U)>,
   len: usize,
   buffer: wgpu::Buffer,
+  _token: Option<ResourceToken>,
 }
 
-impl<T: NoUninit> ArrayBuffer<T> {
-  pub fn len(&self) -> usize {
-    self.len
-  }
-  pub fn vertex_layout<'a>(
+impl<T: NoUninit, U: BufferUsage> ArrayBuffer<T, U> {
+  #[track_caller]
+  pub fn range(
     &self,
-    attributes: &'a [VertexAttribute],
-  ) -> VertexBufferLayout<'a> {
-    VertexBufferLayout {
-      array_stride: std::mem::size_of::<T>() as wgpu::BufferAddress,
-      step_mode: wgpu::VertexStepMode::Vertex,
-      attributes,
-    }
-  }
-  pub fn instance_layout<'a>(
-    &self,
-    attributes: &'a [VertexAttribute],
-  ) -> VertexBufferLayout<'a> {
-    VertexBufferLayout {
-      array_stride: std::mem::size_of::<T>() as wgpu::BufferAddress,
-      step_mode: wgpu::VertexStepMode::Instance,
-      attributes,
-    }
-  }
-  pub fn instance_stepped_vertex_layout<'a>(
-    &self,
-    attributes: &'a [VertexAttribute],
-  ) -> VertexBufferLayout<'a> {
-    VertexBufferLayout {
-      array_stride: std::mem::size_of::<T>() as wgpu::BufferAddress,
-      step_mode: wgpu::VertexStepMode::Instance,
-      attributes,
-    }
-  }
-  pub fn write(&self, wgpu: &WGPUController, position: usize, data: &[T]) {
-    wgpu.queue.write_buffer(
-      &self.buffer,
-      (position * std::mem::size_of::<T>()) as u64,
-      bytemuck::cast_slice(data),
-    )
+    range: impl RangeBounds<usize>,
+  ) -> ArrayBufferRange<'_, T, U> {
+    ArrayBufferRange::new(self, range)
   }
+  #[track_caller]
   pub fn copy_from(
     &self,
-    wgpu: &WGPUController,
-    source: &Self,
-    source_offset: usize,
-    destination_offset: usize,
-    length: usize,
-  ) {
-    let mut encoder = wgpu
-      .device
-      .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder: &mut CommandEncoder,
+    source: ArrayBufferRange<'_, T, impl BufferUsage>,
+    index: usize,
+  ) -> Result<(), BufferWriteError> {
+    check_bounds(index, source.len(), self.len)?;
+    source.assert_copy_aligned("copy");
+    let offset = (index * std::mem::size_of::<T>()) as u64;
+    assert!(
+      offset.is_multiple_of(COPY_BUFFER_ALIGNMENT),
+      "can't copy to index {index}, which is at byte offset {offset}: copy \
+      destinations have to be multiples of {COPY_BUFFER_ALIGNMENT} bytes"
+    );
     encoder.copy_buffer_to_buffer(
-      &source.buffer,
-      (source_offset * std::mem::size_of::<T>()) as u64,
+      source.buffer(),
+      source.byte_offset(),
       &self.buffer,
-      (destination_offset * std::mem::size_of::<T>()) as u64,
-      (length * std::mem::size_of::<T>()) as u64,
+      offset,
+      source.byte_size(),
     );
-    wgpu.queue.submit(std::iter::once(encoder.finish()));
+    Ok(())
+  }
+}
+
+impl<T: NoUninit, U: BufferUsage> GpuBuffer for ArrayBuffer<T, U> {
+  type Element = T;
+  type Usage = U;
+  fn raw_buffer(&self) -> &wgpu::Buffer {
+    &self.buffer
+  }
+  fn len(&self) -> usize {
+    self.len
   }
 }
 
-impl<T: NoUninit> Deref for ArrayBuffer<T> {
+impl<T: NoUninit, U: BufferUsage> Deref for ArrayBuffer<T, U> {
   type Target = wgpu::Buffer;
   fn deref(&self) -> &Self::Target {
     &self.buffer
   }
 }
 
-impl<'b, T: NoUninit> Into<&'b wgpu::Buffer> for &'b ArrayBuffer<T> {
+impl<'b, T: NoUninit, U: BufferUsage> Into<&'b wgpu::Buffer>
+  for &'b ArrayBuffer<T, U>
+{
   fn into(self) -> &'b wgpu::Buffer {
     &*self
   }
 }
 
-impl<'s, T: NoUninit> IntoVertexBufferData<'s> for &'s ArrayBuffer<T> {
-  fn into_vertex_buffer_data(self) -> BufferSlice<'s> {
-    self.slice(..)
-  }
-}
-
 pub(crate) enum Contents<'c> {
   Owned(Vec<u8>),
   Borrowed(&'c [u8]),
 }
 
-pub struct ArrayBufferBuilder<'c, 's, 'w, 'window, T: NoUninit> {
-  _phantom: PhantomData<T>,
+pub struct ArrayBufferBuilder<
+  'c,
+  's,
+  'w,
+  'window,
+  T: NoUninit,
+  U: BufferUsage = AllUsages,
+> {
+  _phantom: PhantomData<(T, U)>,
   initial_contents: Contents<'c>,
   label: Option<&'s str>,
   wgpu: &'w WGPUController<'window>,
@@ -113,7 +106,7 @@ pub struct ArrayBufferBuilder<'c, 's, 'w, 'window, T: NoUninit> {
 }
 
 impl<'c, 's, 'w, 'window, T: NoUninit>
-  ArrayBufferBuilder<'c, 's, 'w, 'window, T>
+  ArrayBufferBuilder<'c, 's, 'w, 'window, T, AllUsages>
 {
   pub fn from_owned_contents(
     wgpu: &'w WGPUController<'window>,
@@ -156,6 +149,22 @@ impl<'c, 's, 'w, 'window, T: NoUninit>
       wgpu,
     }
   }
+}
+
+impl<'c, 's, 'w, 'window, T: NoUninit, U: BufferUsage>
+  ArrayBufferBuilder<'c, 's, 'w, 'window, T, U>
+{
+  pub fn with_usage_type<V: BufferUsage>(
+    self,
+  ) -> ArrayBufferBuilder<'c, 's, 'w, 'window, T, V> {
+    ArrayBufferBuilder {
+      _phantom: PhantomData,
+      initial_contents: self.initial_contents,
+      label: self.label,
+      wgpu: self.wgpu,
+      usage: self.usage,
+    }
+  }
   pub fn with_label(mut self, label: &'s str) -> Self {
     self.label = Some(label);
     self
@@ -164,32 +173,32 @@ impl<'c, 's, 'w, 'window, T: NoUninit>
     self.usage = Some(usage);
     self
   }
-  pub fn build(self) -> ArrayBuffer<T> {
+  #[track_caller]
+  pub fn build(self) -> ArrayBuffer<T, U> {
+    let buffer =
+      self
+        .wgpu
+        .device
+        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+          label: self.label,
+          contents: match &self.initial_contents {
+            Contents::Owned(vec) => &vec,
+            Contents::Borrowed(slice) => slice,
+          },
+          usage: U::usages_with(self.usage),
+        });
     ArrayBuffer {
       _phantom: PhantomData,
       len: match &self.initial_contents {
         Contents::Owned(vec) => vec.len(),
         Contents::Borrowed(slice) => slice.len(),
       } / std::mem::size_of::<T>(),
-      buffer: self.wgpu.device.create_buffer_init(
-        &wgpu::util::BufferInitDescriptor {
-          label: self.label,
-          contents: match &self.initial_contents {
-            Contents::Owned(vec) => &vec,
-            Contents::Borrowed(slice) => slice,
-          },
-          usage: self.usage.unwrap_or(
-            BufferUsages::COPY_SRC
-              | BufferUsages::COPY_DST
-              | BufferUsages::INDEX
-              | BufferUsages::VERTEX
-              | BufferUsages::UNIFORM
-              | BufferUsages::STORAGE
-              | BufferUsages::INDIRECT
-              | BufferUsages::QUERY_RESOLVE,
-          ),
-        },
+      _token: self.wgpu.register_buffer(
+        self.label,
+        buffer.size(),
+        buffer.usage(),
       ),
+      buffer,
     }
   }
 }
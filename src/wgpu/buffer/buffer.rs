@@ -1,91 +1,94 @@
 use std::{marker::PhantomData, ops::Deref};
 
 use bytemuck::NoUninit;
-use wgpu::{
-  util::DeviceExt, BufferSlice, BufferUsages, VertexAttribute,
-  VertexBufferLayout,
-};
+use wgpu::{util::DeviceExt, BufferUsages};
 
-use crate::wgpu::controller::WGPUController;
+use crate::wgpu::{controller::WGPUController, registry::ResourceToken};
 
-use super::data::IntoVertexBufferData;
+use super::{
+  gpu::GpuBuffer,
+  usage::{AllUsages, BufferUsage},
+};
 
 #[derive(Debug, Clone)]
-pub struct Buffer<T: NoUninit> {
-  _phantom: PhantomData<T>,
+pub struct Buffer<T: NoUninit, U: BufferUsage = AllUsages> {
+  _phantom: PhantomData<(T, U)>,
+  len: usize,
   buffer: wgpu::Buffer,
+  _token: Option<ResourceToken>,
 }
 
-impl<T: NoUninit> Buffer<T> {
-  pub fn vertex_layout<'a>(
-    &self,
-    attributes: &'a [VertexAttribute],
-  ) -> VertexBufferLayout<'a> {
-    VertexBufferLayout {
-      array_stride: std::mem::size_of::<T>() as wgpu::BufferAddress,
-      step_mode: wgpu::VertexStepMode::Vertex,
-      attributes,
-    }
-  }
-  pub fn instance_layout<'a>(
-    &self,
-    attributes: &'a [VertexAttribute],
-  ) -> VertexBufferLayout<'a> {
-    VertexBufferLayout {
-      array_stride: std::mem::size_of::<T>() as wgpu::BufferAddress,
-      step_mode: wgpu::VertexStepMode::Instance,
-      attributes,
-    }
+impl<T: NoUninit, U: BufferUsage> GpuBuffer for Buffer<T, U> {
+  type Element = T;
+  type Usage = U;
+  fn raw_buffer(&self) -> &wgpu::Buffer {
+    &self.buffer
   }
-  pub fn instance_stepped_vertex_layout<'a>(
-    &self,
-    attributes: &'a [VertexAttribute],
-  ) -> VertexBufferLayout<'a> {
-    VertexBufferLayout {
-      array_stride: std::mem::size_of::<T>() as wgpu::BufferAddress,
-      step_mode: wgpu::VertexStepMode::Instance,
-      attributes,
-    }
+  fn len(&self) -> usize {
+    self.len
   }
 }
 
-impl<T: NoUninit> Deref for Buffer<T> {
+impl<T: NoUninit, U: BufferUsage> Deref for Buffer<T, U> {
   type Target = wgpu::Buffer;
   fn deref(&self) -> &Self::Target {
     &self.buffer
   }
 }
 
-impl<'b, T: NoUninit> Into<&'b wgpu::Buffer> for &'b Buffer<T> {
+impl<'b, T: NoUninit, U: BufferUsage> Into<&'b wgpu::Buffer>
+  for &'b Buffer<T, U>
+{
   fn into(self) -> &'b wgpu::Buffer {
     &*self
   }
 }
-impl<'s, T: NoUninit> IntoVertexBufferData<'s> for &'s Buffer<T> {
-  fn into_vertex_buffer_data(self) -> BufferSlice<'s> {
-    self.slice(..)
-  }
-}
-
-pub struct BufferBuilder<'c, 's, 'w, 'window, T: NoUninit> {
+pub struct BufferBuilder<
+  'c,
+  's,
+  'w,
+  'window,
+  T: NoUninit,
+  U: BufferUsage = AllUsages,
+> {
+  _phantom: PhantomData<U>,
   initial_contents: &'c [T],
   label: Option<&'s str>,
   wgpu: &'w WGPUController<'window>,
   usage: Option<BufferUsages>,
 }
 
-impl<'c, 's, 'w, 'window, T: NoUninit> BufferBuilder<'c, 's, 'w, 'window, T> {
+impl<'c, 's, 'w, 'window, T: NoUninit>
+  BufferBuilder<'c, 's, 'w, 'window, T, AllUsages>
+{
   pub fn new(
     wgpu: &'w WGPUController<'window>,
     initial_contents: &'c [T],
   ) -> Self {
     Self {
+      _phantom: PhantomData,
       initial_contents,
       label: None,
       usage: None,
       wgpu: wgpu,
     }
   }
+}
+
+impl<'c, 's, 'w, 'window, T: NoUninit, U: BufferUsage>
+  BufferBuilder<'c, 's, 'w, 'window, T, U>
+{
+  pub fn with_usage_type<V: BufferUsage>(
+    self,
+  ) -> BufferBuilder<'c, 's, 'w, 'window, T, V> {
+    BufferBuilder {
+      _phantom: PhantomData,
+      initial_contents: self.initial_contents,
+      label: self.label,
+      wgpu: self.wgpu,
+      usage: self.usage,
+    }
+  }
   pub fn with_label(mut self, label: &'s str) -> Self {
     self.label = Some(label);
     self
@@ -94,25 +97,26 @@ impl<'c, 's, 'w, 'window, T: NoUninit> BufferBuilder<'c, 's, 'w, 'window, T> {
     self.usage = Some(usage);
     self
   }
-  pub fn build(self) -> Buffer<T> {
-    Buffer {
-      _phantom: PhantomData,
-      buffer: self.wgpu.device.create_buffer_init(
-        &wgpu::util::BufferInitDescriptor {
+  #[track_caller]
+  pub fn build(self) -> Buffer<T, U> {
+    let buffer =
+      self
+        .wgpu
+        .device
+        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
           label: self.label,
           contents: bytemuck::cast_slice(self.initial_contents),
-          usage: self.usage.unwrap_or(
-            BufferUsages::COPY_SRC
-              | BufferUsages::COPY_DST
-              | BufferUsages::INDEX
-              | BufferUsages::VERTEX
-              | BufferUsages::UNIFORM
-              | BufferUsages::STORAGE
-              | BufferUsages::INDIRECT
-              | BufferUsages::QUERY_RESOLVE,
-          ),
-        },
+          usage: U::usages_with(self.usage),
+        });
+    Buffer {
+      _phantom: PhantomData,
+      len: self.initial_contents.len(),
+      _token: self.wgpu.register_buffer(
+        self.label,
+        buffer.size(),
+        buffer.usage(),
       ),
+      buffer,
     }
   }
 }
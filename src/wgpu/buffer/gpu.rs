@@ -0,0 +1,127 @@
+use std::{num::NonZero, ops::RangeBounds};
+
+use bytemuck::{AnyBitPattern, NoUninit};
+use wgpu::{BufferBinding, BufferSlice, VertexAttribute, VertexBufferLayout};
+
+use crate::wgpu::{controller::WGPUController, encoder::CommandEncoder};
+
+use super::{
+  data::IntoVertexBufferData,
+  range::element_range,
+  read::read_buffer,
+  usage::{BufferUsage, HasBufferUsage, Supports, Vertex},
+  write::{checked_upload, checked_write, BufferWriteError},
+};
+
+// Capabilities shared by `Buffer`, `ArrayBuffer` and `VectorBuffer`. Indices
+// and ranges are in elements of `Element`, not bytes.
+pub trait GpuBuffer {
+  type Element: NoUninit;
+  type Usage: BufferUsage;
+  fn raw_buffer(&self) -> &wgpu::Buffer;
+  fn len(&self) -> usize;
+  fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+  fn byte_size(&self) -> u64 {
+    (self.len() * std::mem::size_of::<Self::Element>()) as u64
+  }
+  fn vertex_layout<'a>(
+    &self,
+    attributes: &'a [VertexAttribute],
+  ) -> VertexBufferLayout<'a> {
+    VertexBufferLayout {
+      array_stride: std::mem::size_of::<Self::Element>() as wgpu::BufferAddress,
+      step_mode: wgpu::VertexStepMode::Vertex,
+      attributes,
+    }
+  }
+  fn instance_layout<'a>(
+    &self,
+    attributes: &'a [VertexAttribute],
+  ) -> VertexBufferLayout<'a> {
+    VertexBufferLayout {
+      array_stride: std::mem::size_of::<Self::Element>() as wgpu::BufferAddress,
+      step_mode: wgpu::VertexStepMode::Instance,
+      attributes,
+    }
+  }
+  fn instance_stepped_vertex_layout<'a>(
+    &self,
+    attributes: &'a [VertexAttribute],
+  ) -> VertexBufferLayout<'a> {
+    self.instance_layout(attributes)
+  }
+  #[track_caller]
+  fn slice_elements(&self, range: impl RangeBounds<usize>) -> BufferSlice<'_> {
+    let range = element_range(range, self.len());
+    assert!(
+      !range.is_empty(),
+      "can't slice the empty element range {}..{} of a buffer",
+      range.start,
+      range.end
+    );
+    let size = std::mem::size_of::<Self::Element>() as u64;
+    self
+      .raw_buffer()
+      .slice(range.start as u64 * size..range.end as u64 * size)
+  }
+  fn binding(&self, range: impl RangeBounds<usize>) -> BufferBinding<'_> {
+    let range = element_range(range, self.len());
+    let size = std::mem::size_of::<Self::Element>() as u64;
+    BufferBinding {
+      buffer: self.raw_buffer(),
+      offset: range.start as u64 * size,
+      size: Some(
+        NonZero::new(range.len() as u64 * size)
+          .expect("can't bind an empty range of a buffer"),
+      ),
+    }
+  }
+  fn write(
+    &self,
+    wgpu: &WGPUController,
+    index: usize,
+    data: &[Self::Element],
+  ) -> Result<(), BufferWriteError> {
+    checked_write(&wgpu.queue, self.raw_buffer(), self.len(), index, data)
+  }
+  fn upload(
+    &self,
+    encoder: &mut CommandEncoder,
+    wgpu: &WGPUController,
+    index: usize,
+    data: &[Self::Element],
+  ) -> Result<(), BufferWriteError> {
+    checked_upload(encoder, wgpu, self.raw_buffer(), self.len(), index, data)
+  }
+  fn read(&self, wgpu: &WGPUController) -> Vec<Self::Element>
+  where
+    Self::Element: AnyBitPattern,
+  {
+    read_buffer(wgpu, self.raw_buffer(), 0, self.len())
+  }
+  fn read_range(
+    &self,
+    wgpu: &WGPUController,
+    range: impl RangeBounds<usize>,
+  ) -> Vec<Self::Element>
+  where
+    Self::Element: AnyBitPattern,
+  {
+    let range = element_range(range, self.len());
+    read_buffer(wgpu, self.raw_buffer(), range.start, range.len())
+  }
+}
+
+impl<B: GpuBuffer> HasBufferUsage for &B {
+  type Usage = B::Usage;
+}
+
+impl<'s, B: GpuBuffer<Usage: Supports<Vertex>>> IntoVertexBufferData<'s>
+  for &'s B
+{
+  fn into_vertex_buffer_data(self) -> BufferSlice<'s> {
+    self.slice_elements(..self.len())
+  }
+}
@@ -0,0 +1,45 @@
+use bytemuck::{Pod, Zeroable};
+
+use super::{
+  array::ArrayBuffer,
+  usage::{Indirect, Storage},
+};
+
+pub trait IndirectArgs: Pod {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct DrawIndirectArgs {
+  pub vertex_count: u32,
+  pub instance_count: u32,
+  pub first_vertex: u32,
+  pub first_instance: u32,
+}
+impl IndirectArgs for DrawIndirectArgs {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct DrawIndexedIndirectArgs {
+  pub index_count: u32,
+  pub instance_count: u32,
+  pub first_index: u32,
+  pub base_vertex: i32,
+  pub first_instance: u32,
+}
+impl IndirectArgs for DrawIndexedIndirectArgs {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct DispatchIndirectArgs {
+  pub x: u32,
+  pub y: u32,
+  pub z: u32,
+}
+impl IndirectArgs for DispatchIndirectArgs {}
+
+pub type DrawIndirectBuffer<U = (Storage, Indirect)> =
+  ArrayBuffer<DrawIndirectArgs, U>;
+pub type DrawIndexedIndirectBuffer<U = (Storage, Indirect)> =
+  ArrayBuffer<DrawIndexedIndirectArgs, U>;
+pub type DispatchIndirectBuffer<U = (Storage, Indirect)> =
+  ArrayBuffer<DispatchIndirectArgs, U>;
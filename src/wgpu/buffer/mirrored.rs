@@ -0,0 +1,193 @@
+use std::ops::{Deref, Index, IndexMut, Range, RangeBounds};
+
+use bytemuck::{AnyBitPattern, NoUninit};
+use wgpu::{BufferSlice, COPY_BUFFER_ALIGNMENT};
+
+use crate::wgpu::{controller::WGPUController, encoder::CommandEncoder};
+
+use super::{
+  array::{ArrayBuffer, ArrayBufferBuilder},
+  data::IntoVertexBufferData,
+  gpu::GpuBuffer,
+  range::{element_range, ArrayBufferRange},
+  usage::{AllUsages, BufferUsage, Supports, Vertex},
+  write::BufferWriteError,
+};
+
+const MAX_DIRTY_RANGES: usize = 16;
+
+fn gcd(a: usize, b: usize) -> usize {
+  if b == 0 {
+    a
+  } else {
+    gcd(b, a % b)
+  }
+}
+
+fn lcm(a: usize, b: usize) -> usize {
+  a / gcd(a, b) * b
+}
+
+// An `ArrayBuffer` paired with a CPU-side copy of its contents. Mutations of
+// the CPU copy are tracked, and `flush` only uploads the ranges that changed.
+pub struct MirroredArrayBuffer<T: NoUninit, U: BufferUsage = AllUsages> {
+  data: Vec<T>,
+  buffer: ArrayBuffer<T, U>,
+  dirty: Vec<Range<usize>>,
+}
+
+impl<T: NoUninit, U: BufferUsage> MirroredArrayBuffer<T, U> {
+  #[track_caller]
+  pub fn new(wgpu: &WGPUController, data: Vec<T>) -> Self {
+    let buffer = ArrayBufferBuilder::from_contents(wgpu, &data)
+      .with_usage_type()
+      .build();
+    Self {
+      data,
+      buffer,
+      dirty: vec![],
+    }
+  }
+  pub fn buffer(&self) -> &ArrayBuffer<T, U> {
+    &self.buffer
+  }
+  pub fn as_mut_slice(&mut self) -> &mut [T] {
+    self.slice_mut(..)
+  }
+  pub fn slice_mut(&mut self, range: impl RangeBounds<usize>) -> &mut [T] {
+    let range = element_range(range, self.data.len());
+    self.mark_dirty(range.clone());
+    &mut self.data[range]
+  }
+  pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+    if index < self.data.len() {
+      self.mark_dirty(index..index + 1);
+    }
+    self.data.get_mut(index)
+  }
+  pub fn set(&mut self, index: usize, value: T) {
+    self[index] = value;
+  }
+  // Ranges that overlap or touch are merged as they're marked, so writing
+  // neighbouring elements one at a time leaves a single range. Scattered
+  // writes are collapsed into one bounding range past MAX_DIRTY_RANGES.
+  pub fn mark_dirty(&mut self, range: Range<usize>) {
+    if range.is_empty() {
+      return;
+    }
+    let mut merged = range;
+    self.dirty.retain(|dirty| {
+      let touches = dirty.start <= merged.end && merged.start <= dirty.end;
+      if touches {
+        merged = merged.start.min(dirty.start)..merged.end.max(dirty.end);
+      }
+      !touches
+    });
+    self.dirty.push(merged);
+    if self.dirty.len() > MAX_DIRTY_RANGES {
+      let start = self.dirty.iter().map(|range| range.start).min().unwrap();
+      let end = self.dirty.iter().map(|range| range.end).max().unwrap();
+      self.dirty.clear();
+      self.dirty.push(start..end);
+    }
+  }
+  pub fn is_dirty(&self) -> bool {
+    !self.dirty.is_empty()
+  }
+  pub fn dirty_ranges(&self) -> Vec<Range<usize>> {
+    // Writes have to cover whole multiples of COPY_BUFFER_ALIGNMENT bytes, so
+    // ranges are widened to the nearest element boundaries that are also
+    // aligned byte offsets.
+    let size = std::mem::size_of::<T>().max(1);
+    let alignment = lcm(size, COPY_BUFFER_ALIGNMENT as usize) / size;
+    let mut ranges: Vec<Range<usize>> = self
+      .dirty
+      .iter()
+      .map(|range| {
+        (range.start - range.start % alignment)
+          ..range.end.next_multiple_of(alignment).min(self.data.len())
+      })
+      .collect();
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = vec![];
+    for range in ranges {
+      match merged.last_mut() {
+        Some(last) if range.start <= last.end => {
+          last.end = last.end.max(range.end)
+        }
+        _ => merged.push(range),
+      }
+    }
+    merged
+  }
+  pub fn flush(
+    &mut self,
+    wgpu: &WGPUController,
+  ) -> Result<(), BufferWriteError> {
+    for range in self.dirty_ranges() {
+      self.buffer.write(wgpu, range.start, &self.data[range])?;
+    }
+    self.dirty.clear();
+    Ok(())
+  }
+  pub fn flush_with_encoder(
+    &mut self,
+    encoder: &mut CommandEncoder,
+    wgpu: &WGPUController,
+  ) -> Result<(), BufferWriteError> {
+    for range in self.dirty_ranges() {
+      self
+        .buffer
+        .upload(encoder, wgpu, range.start, &self.data[range])?;
+    }
+    self.dirty.clear();
+    Ok(())
+  }
+  pub fn range(
+    &self,
+    range: impl RangeBounds<usize>,
+  ) -> ArrayBufferRange<'_, T, U> {
+    self.buffer.range(range)
+  }
+}
+
+impl<T: NoUninit + AnyBitPattern, U: BufferUsage> MirroredArrayBuffer<T, U> {
+  // Replaces the CPU copy with the buffer's current GPU contents, discarding
+  // any unflushed CPU-side changes.
+  pub fn read_back(&mut self, wgpu: &WGPUController) {
+    self.data = self.buffer.read(wgpu);
+    self.dirty.clear();
+  }
+}
+
+impl<T: NoUninit, U: BufferUsage> Deref for MirroredArrayBuffer<T, U> {
+  type Target = [T];
+  fn deref(&self) -> &Self::Target {
+    &self.data
+  }
+}
+
+impl<T: NoUninit, U: BufferUsage> Index<usize> for MirroredArrayBuffer<T, U> {
+  type Output = T;
+  fn index(&self, index: usize) -> &Self::Output {
+    &self.data[index]
+  }
+}
+
+impl<T: NoUninit, U: BufferUsage> IndexMut<usize>
+  for MirroredArrayBuffer<T, U>
+{
+  fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+    self
+      .get_mut(index)
+      .expect("index out of bounds for MirroredArrayBuffer")
+  }
+}
+
+impl<'s, T: NoUninit, U: Supports<Vertex>> IntoVertexBufferData<'s>
+  for &'s MirroredArrayBuffer<T, U>
+{
+  fn into_vertex_buffer_data(self) -> BufferSlice<'s> {
+    self.buffer.into_vertex_buffer_data()
+  }
+}
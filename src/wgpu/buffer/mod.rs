@@ -1,13 +1,47 @@
+mod append;
 mod array;
 mod buffer;
 mod data;
+mod gpu;
+mod indirect;
+mod mirrored;
+mod range;
+mod read;
+#[cfg(test)]
+mod tests;
+mod usage;
 mod vector;
+mod write;
 
+pub use append::AppendBuffer;
 pub use array::ArrayBuffer;
 pub use array::ArrayBufferBuilder;
 pub use buffer::Buffer;
 pub use buffer::BufferBuilder;
 pub use data::IntoBufferData;
 pub use data::IntoVertexBufferData;
+pub use gpu::GpuBuffer;
+pub use indirect::DispatchIndirectArgs;
+pub use indirect::DispatchIndirectBuffer;
+pub use indirect::DrawIndexedIndirectArgs;
+pub use indirect::DrawIndexedIndirectBuffer;
+pub use indirect::DrawIndirectArgs;
+pub use indirect::DrawIndirectBuffer;
+pub use indirect::IndirectArgs;
+pub use mirrored::MirroredArrayBuffer;
+pub use range::ArrayBufferRange;
+pub use usage::AllUsages;
+pub use usage::BufferUsage;
+pub use usage::HasBufferUsage;
+pub use usage::Index;
+pub use usage::Indirect;
+pub use usage::RawBuffer;
+pub use usage::Storage;
+pub use usage::Supports;
+pub use usage::Uniform;
+pub use usage::Vertex;
 pub use vector::VectorBuffer;
 pub use vector::VectorBufferBuilder;
+pub use write::BufferWriteError;
+
+pub(crate) use read::read_staging;
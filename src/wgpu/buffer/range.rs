@@ -0,0 +1,144 @@
+use std::{
+  num::NonZero,
+  ops::{Bound, Range, RangeBounds},
+};
+
+use bytemuck::NoUninit;
+use wgpu::{BufferBinding, BufferSlice, COPY_BUFFER_ALIGNMENT};
+
+use super::{
+  array::ArrayBuffer,
+  data::IntoVertexBufferData,
+  gpu::GpuBuffer,
+  usage::{BufferUsage, HasBufferUsage, Supports, Vertex},
+};
+
+pub struct ArrayBufferRange<'b, T: NoUninit, U: BufferUsage> {
+  buffer: &'b ArrayBuffer<T, U>,
+  start: usize,
+  end: usize,
+}
+
+impl<T: NoUninit, U: BufferUsage> Clone for ArrayBufferRange<'_, T, U> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+impl<T: NoUninit, U: BufferUsage> Copy for ArrayBufferRange<'_, T, U> {}
+
+pub(crate) fn element_range(
+  range: impl RangeBounds<usize>,
+  len: usize,
+) -> Range<usize> {
+  let start = match range.start_bound() {
+    Bound::Included(&start) => start,
+    Bound::Excluded(&start) => {
+      start.checked_add(1).expect("range start overflows usize")
+    }
+    Bound::Unbounded => 0,
+  };
+  let end = match range.end_bound() {
+    Bound::Included(&end) => {
+      end.checked_add(1).expect("range end overflows usize")
+    }
+    Bound::Excluded(&end) => end,
+    Bound::Unbounded => len,
+  };
+  assert!(
+    start <= end && end <= len,
+    "range {start}..{end} is out of bounds for a buffer of length {len}"
+  );
+  start..end
+}
+
+impl<'b, T: NoUninit, U: BufferUsage> ArrayBufferRange<'b, T, U> {
+  // Empty ranges are rejected up front, since wgpu can't slice or bind them.
+  #[track_caller]
+  pub(super) fn new(
+    buffer: &'b ArrayBuffer<T, U>,
+    range: impl RangeBounds<usize>,
+  ) -> Self {
+    let Range { start, end } = element_range(range, buffer.len());
+    assert!(
+      start < end,
+      "buffer range {start}..{end} is empty, buffer ranges need at least one \
+      element"
+    );
+    Self { buffer, start, end }
+  }
+  pub fn buffer(&self) -> &'b ArrayBuffer<T, U> {
+    self.buffer
+  }
+  pub fn start(&self) -> usize {
+    self.start
+  }
+  pub fn end(&self) -> usize {
+    self.end
+  }
+  pub fn len(&self) -> usize {
+    self.end - self.start
+  }
+  pub fn is_empty(&self) -> bool {
+    self.start == self.end
+  }
+  pub fn byte_offset(&self) -> u64 {
+    (self.start * std::mem::size_of::<T>()) as u64
+  }
+  pub fn byte_size(&self) -> u64 {
+    (self.len() * std::mem::size_of::<T>()) as u64
+  }
+  pub fn slice(&self) -> BufferSlice<'b> {
+    let offset = self.byte_offset();
+    self.buffer.slice(offset..offset + self.byte_size())
+  }
+  // Copies and clears have to start at and cover whole multiples of
+  // COPY_BUFFER_ALIGNMENT bytes.
+  #[track_caller]
+  pub(crate) fn assert_copy_aligned(&self, operation: &str) {
+    assert!(
+      self.byte_offset().is_multiple_of(COPY_BUFFER_ALIGNMENT)
+        && self.byte_size().is_multiple_of(COPY_BUFFER_ALIGNMENT),
+      "can't {operation} buffer range {}..{}, which covers {} bytes at byte \
+      offset {}: both have to be multiples of {COPY_BUFFER_ALIGNMENT}",
+      self.start,
+      self.end,
+      self.byte_size(),
+      self.byte_offset()
+    );
+  }
+  // `offset_alignment` is the device's `min_uniform_buffer_offset_alignment`
+  // or `min_storage_buffer_offset_alignment`, depending on how it's bound.
+  #[track_caller]
+  pub fn binding(&self, offset_alignment: u32) -> BufferBinding<'b> {
+    assert!(
+      self.byte_offset().is_multiple_of(offset_alignment as u64),
+      "buffer range {}..{} starts at byte {}, which isn't a multiple of the \
+      device's {offset_alignment} byte buffer offset alignment",
+      self.start,
+      self.end,
+      self.byte_offset()
+    );
+    BufferBinding {
+      buffer: self.buffer,
+      offset: self.byte_offset(),
+      size: Some(
+        NonZero::new(self.byte_size())
+          .expect("can't bind an empty ArrayBufferRange"),
+      ),
+    }
+  }
+}
+
+impl<T: NoUninit, U: BufferUsage> HasBufferUsage
+  for ArrayBufferRange<'_, T, U>
+{
+  type Usage = U;
+}
+
+impl<'b, T: NoUninit, U: Supports<Vertex>> IntoVertexBufferData<'b>
+  for ArrayBufferRange<'b, T, U>
+{
+  fn into_vertex_buffer_data(self) -> BufferSlice<'b> {
+    self.slice()
+  }
+}
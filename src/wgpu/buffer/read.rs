@@ -0,0 +1,71 @@
+use bytemuck::{AnyBitPattern, NoUninit};
+use wgpu::{BufferUsages, COPY_BUFFER_ALIGNMENT};
+
+use crate::wgpu::{controller::WGPUController, encoder::CommandEncoder};
+
+pub(crate) fn read_buffer<T: NoUninit + AnyBitPattern>(
+  wgpu: &WGPUController,
+  buffer: &wgpu::Buffer,
+  index: usize,
+  count: usize,
+) -> Vec<T> {
+  let mut data = vec![T::zeroed(); count];
+  let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut data);
+  if bytes.is_empty() {
+    return data;
+  }
+  // Copies have to start and end on COPY_BUFFER_ALIGNMENT, so the copied
+  // range is rounded out and the requested bytes are sliced out of it.
+  let offset = (index * std::mem::size_of::<T>()) as u64;
+  let copy_start = offset - offset % COPY_BUFFER_ALIGNMENT;
+  let copy_end =
+    (offset + bytes.len() as u64).next_multiple_of(COPY_BUFFER_ALIGNMENT);
+  let size = copy_end - copy_start;
+  let skip = (offset - copy_start) as usize;
+  let len = bytes.len();
+  read_staging(
+    wgpu,
+    size,
+    |encoder, staging| {
+      encoder.copy_buffer_to_buffer(buffer, copy_start, staging, 0, size);
+    },
+    |mapped| bytes.copy_from_slice(&mapped[skip..skip + len]),
+  );
+  data
+}
+
+// Copies into a mappable staging buffer of `size` bytes with `record`, waits
+// for the copy, and hands the mapped contents to `read`. The copy is submitted
+// without touching the staging belt, so readbacks can happen while another
+// encoder has uploads pending.
+pub(crate) fn read_staging<R>(
+  wgpu: &WGPUController,
+  size: u64,
+  record: impl FnOnce(&mut CommandEncoder, &wgpu::Buffer),
+  read: impl FnOnce(&[u8]) -> R,
+) -> R {
+  let staging = wgpu.device.create_buffer(&wgpu::BufferDescriptor {
+    label: Some("readback staging buffer"),
+    size,
+    usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+    mapped_at_creation: false,
+  });
+  wgpu.submit_without_belt(|encoder| record(encoder, &staging));
+  let (sender, receiver) = std::sync::mpsc::channel();
+  staging
+    .slice(..)
+    .map_async(wgpu::MapMode::Read, move |result| {
+      let _ = sender.send(result);
+    });
+  wgpu
+    .device
+    .poll(wgpu::PollType::wait_indefinitely())
+    .expect("failed to wait for buffer readback");
+  receiver
+    .recv()
+    .expect("buffer readback callback was never invoked")
+    .expect("failed to map buffer for reading");
+  let result = read(&staging.slice(..).get_mapped_range());
+  staging.unmap();
+  result
+}
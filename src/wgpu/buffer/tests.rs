@@ -0,0 +1,123 @@
+use crate::wgpu::{controller::WGPUController, texture::TextureBuilder};
+
+use super::{GpuBuffer, IntoVertexBufferData, MirroredArrayBuffer};
+
+#[test]
+fn readback_inside_upload() {
+  let wgpu = WGPUController::for_tests();
+  let uploaded = wgpu.array_buffer(&[0u32; 4]);
+  let other = wgpu.array_buffer(&[5u32, 6, 7, 8]);
+  wgpu.with_encoder(|encoder| {
+    encoder
+      .upload_array_buffer(&wgpu, &uploaded, 0, &[1, 2, 3, 4])
+      .unwrap();
+    assert_eq!(other.read(&wgpu), [5, 6, 7, 8]);
+    assert_eq!(other.read_range(&wgpu, 1..3), [6, 7]);
+    let image = image::RgbaImage::from_pixel(2, 2, image::Rgba([1, 2, 3, 4]));
+    let texture = wgpu
+      .build_texture_from_image(image.into())
+      .with_usage(wgpu::TextureUsages::COPY_SRC)
+      .build();
+    assert_eq!(texture.read(&wgpu).bytes, [1, 2, 3, 4].repeat(4));
+  });
+  assert_eq!(uploaded.read(&wgpu), [1, 2, 3, 4]);
+}
+
+#[test]
+fn mirrored_dirty_ranges_stay_bounded() {
+  let wgpu = WGPUController::for_tests();
+  let mut mirrored: MirroredArrayBuffer<u32> =
+    MirroredArrayBuffer::new(&wgpu, vec![0; 1024]);
+  for i in 100..600 {
+    mirrored[i] = i as u32;
+  }
+  assert_eq!(mirrored.dirty_ranges(), [100..600]);
+  for i in (700..1000).step_by(10) {
+    mirrored.set(i, 1);
+  }
+  let dirty = mirrored.dirty_ranges();
+  assert!(dirty.len() <= 16, "{} dirty ranges", dirty.len());
+  assert_eq!(dirty.first().map(|range| range.start), Some(100));
+  assert_eq!(dirty.last().map(|range| range.end), Some(991));
+  mirrored.flush(&wgpu).unwrap();
+  assert!(!mirrored.is_dirty());
+  assert_eq!(mirrored.buffer().read(&wgpu), mirrored.to_vec());
+}
+
+// Rows of 3 and 1 texels need one copy per row, rows of 64 texels are
+// already aligned and go in a single copy.
+#[test]
+fn packed_texture_copies() {
+  let wgpu = WGPUController::for_tests();
+  for (width, height) in [(3, 5), (64, 3)] {
+    let texture = TextureBuilder::new_2d(&wgpu, width, height)
+      .with_format(wgpu::TextureFormat::Rgba8Unorm)
+      .with_usage(wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST)
+      .with_mip_level_count(2)
+      .build();
+    for mip_level in 0..2 {
+      let texels = (width >> mip_level) * (height >> mip_level);
+      let data: Vec<u8> = (0..texels * 4).map(|i| (i % 251) as u8).collect();
+      let source = wgpu.array_buffer(&data);
+      let destination = wgpu.array_buffer(&vec![0u8; data.len()]);
+      wgpu.with_encoder(|encoder| {
+        encoder
+          .copy_buffer_to_texture_level(&source, &texture, mip_level)
+          .copy_texture_level_to_buffer(&texture, mip_level, &destination);
+      });
+      assert_eq!(
+        destination.read(&wgpu),
+        data,
+        "mip level {mip_level} of a {width}x{height} texture"
+      );
+    }
+  }
+}
+
+// The test device has no CLEAR_TEXTURE, so both clears go through render
+// passes on every mip level and layer.
+#[test]
+fn clear_texture_without_clear_feature() {
+  let wgpu = WGPUController::for_tests();
+  let texture = TextureBuilder::new_2d_array(&wgpu, 4, 4, 2)
+    .with_format(wgpu::TextureFormat::Rgba8Unorm)
+    .with_usage(
+      wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    )
+    .with_mip_level_count(2)
+    .build();
+  let red = wgpu::Color {
+    r: 1.,
+    g: 0.,
+    b: 0.,
+    a: 1.,
+  };
+  for (color, texel) in
+    [(red, [255, 0, 0, 255]), (wgpu::Color::TRANSPARENT, [0; 4])]
+  {
+    let levels = [
+      wgpu.array_buffer(&[0u8; 128]),
+      wgpu.array_buffer(&[1u8; 32]),
+    ];
+    wgpu.with_encoder(|encoder| {
+      encoder.clear_texture_to_color(&wgpu, &texture, color);
+      for (mip_level, level) in levels.iter().enumerate() {
+        encoder.copy_texture_level_to_buffer(&texture, mip_level as u32, level);
+      }
+    });
+    for level in &levels {
+      let data = level.read(&wgpu);
+      assert_eq!(data, texel.repeat(data.len() / 4), "clear to {color:?}");
+    }
+  }
+}
+
+#[test]
+fn vertex_data_covers_only_live_elements() {
+  let wgpu = WGPUController::for_tests();
+  let mut vector = wgpu.vector_buffer::<u32>();
+  vector.overwrite(&wgpu, &[1, 2, 3, 4]);
+  vector.overwrite(&wgpu, &[5, 6]);
+  assert_eq!(vector.len(), 2);
+  assert_eq!(u64::from((&vector).into_vertex_buffer_data().size()), 8);
+}
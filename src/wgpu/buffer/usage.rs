@@ -0,0 +1,127 @@
+use std::marker::PhantomData;
+
+use wgpu::BufferUsages;
+
+pub trait BufferUsage {
+  const USAGES: BufferUsages;
+  fn usages_with(extra: Option<BufferUsages>) -> BufferUsages {
+    Self::USAGES | extra.unwrap_or(BufferUsages::empty())
+  }
+}
+
+pub trait Supports<U: BufferUsage>: BufferUsage {}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Uniform;
+#[derive(Debug, Clone, Copy)]
+pub struct Storage;
+#[derive(Debug, Clone, Copy)]
+pub struct Vertex;
+#[derive(Debug, Clone, Copy)]
+pub struct Index;
+#[derive(Debug, Clone, Copy)]
+pub struct Indirect;
+
+// Every usage flag at once, the default for buffers that don't specify a
+// usage. Satisfies every capability check.
+#[derive(Debug, Clone, Copy)]
+pub struct AllUsages;
+
+macro_rules! single_usage {
+  ($($marker:ident => $flag:ident),+ $(,)?) => {
+    $(
+      impl BufferUsage for $marker {
+        const USAGES: BufferUsages = BufferUsages::COPY_SRC
+          .union(BufferUsages::COPY_DST)
+          .union(BufferUsages::$flag);
+      }
+      impl Supports<$marker> for $marker {}
+      impl Supports<$marker> for AllUsages {}
+    )+
+  };
+}
+
+single_usage!(
+  Uniform => UNIFORM,
+  Storage => STORAGE,
+  Vertex => VERTEX,
+  Index => INDEX,
+  Indirect => INDIRECT,
+);
+
+impl BufferUsage for AllUsages {
+  const USAGES: BufferUsages = BufferUsages::COPY_SRC
+    .union(BufferUsages::COPY_DST)
+    .union(BufferUsages::INDEX)
+    .union(BufferUsages::VERTEX)
+    .union(BufferUsages::UNIFORM)
+    .union(BufferUsages::STORAGE)
+    .union(BufferUsages::INDIRECT)
+    .union(BufferUsages::QUERY_RESOLVE);
+  // Untyped buffers aren't checked at compile time anyway, so an explicit
+  // usage replaces the defaults rather than adding to them.
+  fn usages_with(usage: Option<BufferUsages>) -> BufferUsages {
+    usage.unwrap_or(Self::USAGES)
+  }
+}
+
+macro_rules! combined_usage {
+  ($($marker:ident),+) => {
+    combined_usage!(@impl ($($marker),+); $($marker),+);
+  };
+  (@impl $combined:ty; $($marker:ident),+) => {
+    impl BufferUsage for $combined {
+      const USAGES: BufferUsages =
+        BufferUsages::empty()$(.union($marker::USAGES))+;
+    }
+    $(impl Supports<$marker> for $combined {})+
+  };
+}
+
+combined_usage!(Uniform, Storage);
+combined_usage!(Uniform, Vertex);
+combined_usage!(Storage, Vertex);
+combined_usage!(Storage, Index);
+combined_usage!(Storage, Indirect);
+combined_usage!(Vertex, Index);
+combined_usage!(Storage, Vertex, Index);
+
+pub trait HasBufferUsage {
+  type Usage: BufferUsage;
+}
+
+// Opts a raw wgpu buffer into the typed bind group helpers, checking its usage
+// when it's wrapped instead of at compile time.
+#[derive(Debug, Clone, Copy)]
+pub struct RawBuffer<'b, U: BufferUsage> {
+  buffer: &'b wgpu::Buffer,
+  _phantom: PhantomData<U>,
+}
+
+impl<'b, U: BufferUsage> RawBuffer<'b, U> {
+  #[track_caller]
+  pub fn new(buffer: &'b wgpu::Buffer) -> Self {
+    let required =
+      U::USAGES.difference(BufferUsages::COPY_SRC | BufferUsages::COPY_DST);
+    assert!(
+      buffer.usage().contains(required),
+      "buffer with usage {:?} is missing {:?}",
+      buffer.usage(),
+      required.difference(buffer.usage())
+    );
+    Self {
+      buffer,
+      _phantom: PhantomData,
+    }
+  }
+}
+
+impl<U: BufferUsage> HasBufferUsage for RawBuffer<'_, U> {
+  type Usage = U;
+}
+
+impl<'b, U: BufferUsage> From<RawBuffer<'b, U>> for &'b wgpu::Buffer {
+  fn from(buffer: RawBuffer<'b, U>) -> Self {
+    buffer.buffer
+  }
+}
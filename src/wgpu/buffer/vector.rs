@@ -1,120 +1,119 @@
 use std::{marker::PhantomData, ops::Deref};
 
 use bytemuck::NoUninit;
-use wgpu::{
-  util::DeviceExt, BufferSlice, BufferUsages, VertexAttribute,
-  VertexBufferLayout,
-};
+use wgpu::{util::DeviceExt, BufferUsages};
 
-use crate::wgpu::controller::WGPUController;
+use crate::wgpu::{controller::WGPUController, registry::ResourceToken};
 
-use super::data::IntoVertexBufferData;
+use super::{
+  gpu::GpuBuffer,
+  usage::{AllUsages, BufferUsage},
+  write::checked_write,
+};
 
-pub struct VectorBuffer<T: NoUninit> {
-  _phantom: PhantomData<T>,
+pub struct VectorBuffer<T: NoUninit, U: BufferUsage = AllUsages> {
+  _phantom: PhantomData<(T, U)>,
   len: usize,
   buffer: wgpu::Buffer,
   usage: BufferUsages,
+  label: Option<String>,
+  _token: Option<ResourceToken>,
 }
 
-impl<T: NoUninit> VectorBuffer<T> {
+impl<T: NoUninit, U: BufferUsage> VectorBuffer<T, U> {
+  #[track_caller]
   pub fn empty(wgpu: &WGPUController, usage: BufferUsages) -> Self {
-    Self {
-      _phantom: PhantomData,
-      len: 0,
-      buffer: wgpu.device.create_buffer_init(
-        &wgpu::util::BufferInitDescriptor {
+    let usage = U::usages_with(Some(usage));
+    let buffer =
+      wgpu
+        .device
+        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
           label: None,
           contents: &[],
           usage,
-        },
-      ),
+        });
+    Self {
+      _phantom: PhantomData,
+      len: 0,
+      _token: wgpu.register_buffer(None, buffer.size(), usage),
+      buffer,
       usage,
+      label: None,
     }
   }
-  pub fn len(&self) -> usize {
-    self.len
-  }
-  pub fn vertex_layout<'a>(
-    &self,
-    attributes: &'a [VertexAttribute],
-  ) -> VertexBufferLayout<'a> {
-    VertexBufferLayout {
-      array_stride: std::mem::size_of::<T>() as wgpu::BufferAddress,
-      step_mode: wgpu::VertexStepMode::Vertex,
-      attributes,
-    }
-  }
-  pub fn instance_layout<'a>(
-    &self,
-    attributes: &'a [VertexAttribute],
-  ) -> VertexBufferLayout<'a> {
-    VertexBufferLayout {
-      array_stride: std::mem::size_of::<T>() as wgpu::BufferAddress,
-      step_mode: wgpu::VertexStepMode::Instance,
-      attributes,
-    }
-  }
-  pub fn instance_stepped_vertex_layout<'a>(
-    &self,
-    attributes: &'a [VertexAttribute],
-  ) -> VertexBufferLayout<'a> {
-    VertexBufferLayout {
-      array_stride: std::mem::size_of::<T>() as wgpu::BufferAddress,
-      step_mode: wgpu::VertexStepMode::Instance,
-      attributes,
-    }
-  }
+  #[track_caller]
   fn expand_with(&mut self, wgpu: &WGPUController, data: &[T]) {
     self.len = data.len();
     self.buffer =
       wgpu
         .device
         .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-          label: None,
+          label: self.label.as_deref(),
           contents: bytemuck::cast_slice(data),
           usage: self.usage,
         });
+    self._token = wgpu.register_buffer(
+      self.label.as_deref(),
+      self.buffer.size(),
+      self.usage,
+    );
   }
+  #[track_caller]
   pub fn overwrite(&mut self, wgpu: &WGPUController, data: &[T]) {
     if data.len() > self.len {
       self.expand_with(wgpu, data);
     } else {
-      wgpu
-        .queue
-        .write_buffer(&self.buffer, 0, bytemuck::cast_slice(data));
       self.len = data.len();
+      // The buffer was created for at least `len` elements, so the write
+      // can always be padded out to the copy alignment.
+      checked_write(&wgpu.queue, &self.buffer, self.len, 0, data)
+        .unwrap_or_else(|err| panic!("{err}"));
     }
   }
 }
 
-impl<T: NoUninit> Deref for VectorBuffer<T> {
+impl<T: NoUninit, U: BufferUsage> GpuBuffer for VectorBuffer<T, U> {
+  type Element = T;
+  type Usage = U;
+  fn raw_buffer(&self) -> &wgpu::Buffer {
+    &self.buffer
+  }
+  fn len(&self) -> usize {
+    self.len
+  }
+}
+
+impl<T: NoUninit, U: BufferUsage> Deref for VectorBuffer<T, U> {
   type Target = wgpu::Buffer;
   fn deref(&self) -> &Self::Target {
     &self.buffer
   }
 }
 
-impl<'b, T: NoUninit> Into<&'b wgpu::Buffer> for &'b VectorBuffer<T> {
+impl<'b, T: NoUninit, U: BufferUsage> Into<&'b wgpu::Buffer>
+  for &'b VectorBuffer<T, U>
+{
   fn into(self) -> &'b wgpu::Buffer {
     &*self
   }
 }
 
-impl<'s, T: NoUninit> IntoVertexBufferData<'s> for &'s VectorBuffer<T> {
-  fn into_vertex_buffer_data(self) -> BufferSlice<'s> {
-    self.slice(..)
-  }
-}
-
-pub struct VectorBufferBuilder<'s, 'w, 'window, T: NoUninit> {
-  _phantom: PhantomData<T>,
+pub struct VectorBufferBuilder<
+  's,
+  'w,
+  'window,
+  T: NoUninit,
+  U: BufferUsage = AllUsages,
+> {
+  _phantom: PhantomData<(T, U)>,
   label: Option<&'s str>,
   wgpu: &'w WGPUController<'window>,
   usage: Option<BufferUsages>,
 }
 
-impl<'s, 'w, 'window, T: NoUninit> VectorBufferBuilder<'s, 'w, 'window, T> {
+impl<'s, 'w, 'window, T: NoUninit>
+  VectorBufferBuilder<'s, 'w, 'window, T, AllUsages>
+{
   pub fn new(wgpu: &'w WGPUController<'window>) -> Self {
     Self {
       _phantom: PhantomData,
@@ -123,6 +122,21 @@ impl<'s, 'w, 'window, T: NoUninit> VectorBufferBuilder<'s, 'w, 'window, T> {
       wgpu: wgpu,
     }
   }
+}
+
+impl<'s, 'w, 'window, T: NoUninit, U: BufferUsage>
+  VectorBufferBuilder<'s, 'w, 'window, T, U>
+{
+  pub fn with_usage_type<V: BufferUsage>(
+    self,
+  ) -> VectorBufferBuilder<'s, 'w, 'window, T, V> {
+    VectorBufferBuilder {
+      _phantom: PhantomData,
+      label: self.label,
+      wgpu: self.wgpu,
+      usage: self.usage,
+    }
+  }
   pub fn with_label(mut self, label: &'s str) -> Self {
     self.label = Some(label);
     self
@@ -131,29 +145,26 @@ impl<'s, 'w, 'window, T: NoUninit> VectorBufferBuilder<'s, 'w, 'window, T> {
     self.usage = Some(usage);
     self
   }
-  pub fn build(self) -> VectorBuffer<T> {
-    let usage = self.usage.unwrap_or(
-      BufferUsages::COPY_SRC
-        | BufferUsages::COPY_DST
-        | BufferUsages::INDEX
-        | BufferUsages::VERTEX
-        | BufferUsages::UNIFORM
-        | BufferUsages::STORAGE
-        | BufferUsages::INDIRECT
-        | BufferUsages::QUERY_RESOLVE,
-    );
+  #[track_caller]
+  pub fn build(self) -> VectorBuffer<T, U> {
+    let usage = U::usages_with(self.usage);
     let empty: &[T] = &[];
-    VectorBuffer {
-      _phantom: PhantomData,
-      len: 0,
-      buffer: self.wgpu.device.create_buffer_init(
-        &wgpu::util::BufferInitDescriptor {
+    let buffer =
+      self
+        .wgpu
+        .device
+        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
           label: self.label,
           contents: bytemuck::cast_slice(empty),
           usage,
-        },
-      ),
+        });
+    VectorBuffer {
+      _phantom: PhantomData,
+      len: 0,
+      _token: self.wgpu.register_buffer(self.label, buffer.size(), usage),
+      buffer,
       usage,
+      label: self.label.map(str::to_owned),
     }
   }
 }
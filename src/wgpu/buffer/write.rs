@@ -0,0 +1,139 @@
+use std::{borrow::Cow, fmt::Display, num::NonZero};
+
+use bytemuck::NoUninit;
+use wgpu::COPY_BUFFER_ALIGNMENT;
+
+use crate::wgpu::controller::WGPUController;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferWriteError {
+  OutOfBounds {
+    index: usize,
+    count: usize,
+    len: usize,
+  },
+  // Writes have to start at and span a multiple of COPY_BUFFER_ALIGNMENT
+  // bytes.
+  Misaligned {
+    index: usize,
+    count: usize,
+    byte_offset: u64,
+    byte_len: u64,
+  },
+}
+
+impl Display for BufferWriteError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      BufferWriteError::OutOfBounds { index, count, len } => write!(
+        f,
+        "attempted to write {count} elements at index {index} of a buffer \
+        with length {len}"
+      ),
+      BufferWriteError::Misaligned {
+        index,
+        count,
+        byte_offset,
+        byte_len,
+      } => write!(
+        f,
+        "attempted to write {count} elements at index {index}, which covers \
+        {byte_len} bytes at byte offset {byte_offset}, but buffer writes must \
+        start at and span a multiple of {COPY_BUFFER_ALIGNMENT} bytes"
+      ),
+    }
+  }
+}
+
+impl std::error::Error for BufferWriteError {}
+
+pub(crate) fn check_bounds(
+  index: usize,
+  count: usize,
+  len: usize,
+) -> Result<(), BufferWriteError> {
+  match index.checked_add(count) {
+    Some(end) if end <= len => Ok(()),
+    _ => Err(BufferWriteError::OutOfBounds { index, count, len }),
+  }
+}
+
+pub(crate) fn checked_write<T: NoUninit>(
+  queue: &wgpu::Queue,
+  buffer: &wgpu::Buffer,
+  len: usize,
+  index: usize,
+  data: &[T],
+) -> Result<(), BufferWriteError> {
+  check_bounds(index, data.len(), len)?;
+  let offset = (index * std::mem::size_of::<T>()) as u64;
+  let bytes = padded_bytes(buffer, offset, index + data.len() == len, data);
+  check_aligned(offset, index, bytes.len(), data.len())?;
+  queue.write_buffer(buffer, offset, &bytes);
+  Ok(())
+}
+
+// Buffers are padded to COPY_BUFFER_ALIGNMENT, so a write reaching the last
+// element can be padded out with zeroes when the element size doesn't divide
+// the alignment.
+fn padded_bytes<'d, T: NoUninit>(
+  buffer: &wgpu::Buffer,
+  offset: u64,
+  reaches_end: bool,
+  data: &'d [T],
+) -> Cow<'d, [u8]> {
+  let bytes: &[u8] = bytemuck::cast_slice(data);
+  let padded_len = (bytes.len() as u64).next_multiple_of(COPY_BUFFER_ALIGNMENT);
+  if reaches_end
+    && padded_len != bytes.len() as u64
+    && offset + padded_len <= buffer.size()
+  {
+    let mut padded = bytes.to_vec();
+    padded.resize(padded_len as usize, 0);
+    Cow::Owned(padded)
+  } else {
+    Cow::Borrowed(bytes)
+  }
+}
+
+fn check_aligned(
+  offset: u64,
+  index: usize,
+  bytes: usize,
+  count: usize,
+) -> Result<(), BufferWriteError> {
+  if offset.is_multiple_of(COPY_BUFFER_ALIGNMENT)
+    && (bytes as u64).is_multiple_of(COPY_BUFFER_ALIGNMENT)
+  {
+    Ok(())
+  } else {
+    Err(BufferWriteError::Misaligned {
+      index,
+      count,
+      byte_offset: offset,
+      byte_len: bytes as u64,
+    })
+  }
+}
+
+pub(crate) fn checked_upload<T: NoUninit>(
+  encoder: &mut wgpu::CommandEncoder,
+  wgpu: &WGPUController,
+  buffer: &wgpu::Buffer,
+  len: usize,
+  index: usize,
+  data: &[T],
+) -> Result<(), BufferWriteError> {
+  check_bounds(index, data.len(), len)?;
+  let offset = (index * std::mem::size_of::<T>()) as u64;
+  let bytes = padded_bytes(buffer, offset, index + data.len() == len, data);
+  check_aligned(offset, index, bytes.len(), data.len())?;
+  if let Some(size) = NonZero::new(bytes.len() as u64) {
+    wgpu
+      .staging_belt
+      .borrow_mut()
+      .write_buffer(encoder, buffer, offset, size, &wgpu.device)
+      .copy_from_slice(&bytes);
+  }
+  Ok(())
+}
@@ -1,5 +1,9 @@
 use std::ops::{Deref, DerefMut};
 
+use wgpu::Extent3d;
+
+use super::buffer::{ArrayBuffer, DispatchIndirectArgs, Indirect, Supports};
+
 pub struct ComputePass<'p> {
   pass: wgpu::ComputePass<'p>,
 }
@@ -57,4 +61,40 @@ impl<'p> ComputePass<'p> {
     self.dispatch_workgroups(x, y, z);
     self
   }
+  // Dispatches enough workgroups of `workgroup_size` to cover every texel of
+  // `size`, so shaders should skip invocations outside of it.
+  pub fn dispatch_for_size(
+    self,
+    size: Extent3d,
+    workgroup_size: [u32; 3],
+  ) -> Self {
+    let [x, y, z] = workgroup_size;
+    self.dispatch(
+      size.width.div_ceil(x),
+      size.height.div_ceil(y),
+      size.depth_or_array_layers.div_ceil(z),
+    )
+  }
+  pub fn dispatch_for_texture(
+    self,
+    texture: &wgpu::Texture,
+    mip_level: u32,
+    workgroup_size: [u32; 3],
+  ) -> Self {
+    self.dispatch_for_size(
+      texture
+        .size()
+        .mip_level_size(mip_level, texture.dimension()),
+      workgroup_size,
+    )
+  }
+  pub fn dispatch_indirect<U: Supports<Indirect>>(
+    mut self,
+    args: &ArrayBuffer<DispatchIndirectArgs, U>,
+    index: usize,
+  ) -> Self {
+    let range = args.range(index..index + 1);
+    self.dispatch_workgroups_indirect(args, range.byte_offset());
+    self
+  }
 }
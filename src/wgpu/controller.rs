@@ -1,27 +1,69 @@
-use std::sync::Arc;
+use std::{
+  cell::{Cell, Ref, RefCell},
+  collections::HashMap,
+  panic::Location,
+  path::Path,
+  rc::{Rc, Weak},
+  sync::Arc,
+};
 
 use bytemuck::{NoUninit, Zeroable};
-use wgpu::{Features, ShaderModule, ShaderModuleDescriptor};
+use wgpu::{
+  util::StagingBelt, BufferUsages, Features, Sampler, ShaderModule,
+  ShaderModuleDescriptor, TextureDescriptor, TextureFormat, TextureView,
+};
 use winit::window::Window;
 
 use super::{
-  bind::{BindGroupLayoutBuilder, BindGroupWithLayoutBuilder},
+  arena::UniformArena,
+  atlas::TextureAtlasBuilder,
+  bind::{
+    BindGroupLayoutBuilder, BindGroupWithLayout, BindGroupWithLayoutBuilder,
+  },
   buffer::{
-    ArrayBuffer, ArrayBufferBuilder, Buffer, BufferBuilder, IntoBufferData,
-    VectorBuffer, VectorBufferBuilder,
+    AppendBuffer, ArrayBuffer, ArrayBufferBuilder, Buffer, BufferBuilder,
+    BufferUsage, BufferWriteError, GpuBuffer, Index, Indirect, IndirectArgs,
+    IntoBufferData, MirroredArrayBuffer, Storage, Uniform, VectorBuffer,
+    VectorBufferBuilder, Vertex,
   },
   encoder::CommandEncoder,
+  hdr::{HdrOutput, HdrSettings, HDR_FORMAT},
+  image::{
+    decode_image_bytes, decode_image_file, ImageLoadError, ImageTextureBuilder,
+  },
+  mipmap::MipmapCache,
+  ping_pong::PingPongTexture,
   pipeline::{ComputePipelineBuilder, RenderPipelineBuilder},
-  texture::TextureBuilder,
+  registry::{
+    texture_size, ResourceRecord, ResourceRegistry, ResourceToken,
+    ResourceUsage,
+  },
+  sampler::{SamplerBuilder, SamplerPreset},
+  surface_target::{
+    SurfaceBindGroup, SurfaceSize, SurfaceTarget, SurfaceTargetState,
+  },
+  texture::{Texture, TextureBuilder},
 };
 
 pub struct WGPUController<'window> {
-  pub surface: wgpu::Surface<'window>,
+  // `None` for headless controllers.
+  pub surface: Option<wgpu::Surface<'window>>,
   pub device: wgpu::Device,
   pub queue: wgpu::Queue,
   pub config: wgpu::SurfaceConfiguration,
+  frame_index: Cell<usize>,
+  in_frame: Cell<bool>,
+  pub(crate) staging_belt: RefCell<StagingBelt>,
+  registry: RefCell<Option<ResourceRegistry>>,
+  pub(crate) mipmaps: MipmapCache,
+  samplers: RefCell<HashMap<SamplerPreset, Sampler>>,
+  surface_targets: RefCell<Vec<Weak<SurfaceTargetState>>>,
+  surface_generation: Cell<usize>,
+  hdr: RefCell<Option<HdrOutput>>,
 }
 
+const STAGING_BELT_CHUNK_SIZE: u64 = 1 << 20;
+
 impl<'window> WGPUController<'window> {
   pub async fn new_with_features(
     window: Arc<Window>,
@@ -71,16 +113,243 @@ impl<'window> WGPUController<'window> {
       desired_maximum_frame_latency: 2,
     };
     surface.configure(&device, &config);
+    Self::from_parts(Some(surface), device, queue, config)
+  }
+  // A controller without a window, for compute work and tests. `config`
+  // describes a nominal 1x1 sRGB target, so surface-sized resources can still
+  // be created.
+  pub async fn new_headless(
+    features: Features,
+    force_fallback_adapter: bool,
+  ) -> Self {
+    let wgpu_instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+      backends: wgpu::Backends::all(),
+      ..Default::default()
+    });
+    let adapter = wgpu_instance
+      .request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        compatible_surface: None,
+        force_fallback_adapter,
+      })
+      .await
+      .unwrap();
+    let (device, queue) = adapter
+      .request_device(&wgpu::DeviceDescriptor {
+        required_features: features,
+        required_limits: wgpu::Limits::default(),
+        label: None,
+        memory_hints: Default::default(),
+        experimental_features: wgpu::ExperimentalFeatures::disabled(),
+        trace: wgpu::Trace::Off,
+      })
+      .await
+      .unwrap();
+    let config = wgpu::SurfaceConfiguration {
+      usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+      format: TextureFormat::Rgba8UnormSrgb,
+      width: 1,
+      height: 1,
+      present_mode: wgpu::PresentMode::Fifo,
+      alpha_mode: wgpu::CompositeAlphaMode::Auto,
+      view_formats: vec![],
+      desired_maximum_frame_latency: 2,
+    };
+    Self::from_parts(None, device, queue, config)
+  }
+  fn from_parts(
+    surface: Option<wgpu::Surface<'window>>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+  ) -> Self {
     Self {
       surface,
       device,
       queue,
       config,
+      frame_index: Cell::new(0),
+      in_frame: Cell::new(false),
+      staging_belt: RefCell::new(StagingBelt::new(STAGING_BELT_CHUNK_SIZE)),
+      registry: RefCell::new(None),
+      mipmaps: MipmapCache::default(),
+      samplers: RefCell::new(HashMap::new()),
+      surface_targets: RefCell::new(vec![]),
+      surface_generation: Cell::new(0),
+      hdr: RefCell::new(None),
     }
   }
   pub async fn new(window: Arc<Window>) -> Self {
     Self::new_with_features(window, Features::empty()).await
   }
+  pub fn frame_index(&self) -> usize {
+    self.frame_index.get()
+  }
+  pub fn begin_frame(&self) {
+    self.in_frame.set(true);
+  }
+  pub fn end_frame(&self) {
+    if let Some(registry) = self.registry.borrow_mut().as_mut() {
+      registry.end_frame(self.frame_index.get());
+    }
+    self.in_frame.set(false);
+    self.frame_index.set(self.frame_index.get() + 1);
+  }
+  // Reconfigures the surface and recreates every surface target to match.
+  pub fn resize_surface(&mut self, width: u32, height: u32) {
+    if width == 0 || height == 0 {
+      return;
+    }
+    self.config.width = width;
+    self.config.height = height;
+    if let Some(surface) = &self.surface {
+      surface.configure(&self.device, &self.config);
+    }
+    self
+      .surface_targets
+      .borrow_mut()
+      .retain(|target| match target.upgrade() {
+        Some(target) => {
+          target.resize(self, width, height);
+          true
+        }
+        None => false,
+      });
+    self
+      .surface_generation
+      .set(self.surface_generation.get() + 1);
+  }
+  // Sketches render into an Rgba16Float scene target instead of the surface,
+  // which is tonemapped onto it at the end of each frame. Calling this again
+  // just changes the settings.
+  pub fn enable_hdr(&self, settings: HdrSettings) {
+    if let Some(output) = self.hdr.borrow_mut().as_mut() {
+      output.set_settings(self, settings);
+      return;
+    }
+    let output = HdrOutput::new(self, settings);
+    *self.hdr.borrow_mut() = Some(output);
+  }
+  pub fn hdr_settings(&self) -> Option<HdrSettings> {
+    self.hdr.borrow().as_ref().map(HdrOutput::settings)
+  }
+  pub fn is_hdr(&self) -> bool {
+    self.hdr.borrow().is_some()
+  }
+  // The format sketches render to, which pipelines and surface targets
+  // default to.
+  pub fn render_format(&self) -> TextureFormat {
+    if self.is_hdr() {
+      HDR_FORMAT
+    } else {
+      self.config.format
+    }
+  }
+  pub(crate) fn scene_view(&self, surface_view: &TextureView) -> TextureView {
+    match self.hdr.borrow().as_ref() {
+      Some(output) => output.scene_view(),
+      None => surface_view.clone(),
+    }
+  }
+  pub(crate) fn finish_scene(&self, surface_view: &TextureView) {
+    if let Some(output) = self.hdr.borrow_mut().as_mut() {
+      self.with_encoder(|encoder| output.tonemap(self, encoder, surface_view));
+    }
+  }
+  // Incremented on every resize, so anything built from surface targets can
+  // tell when it's stale.
+  pub fn surface_generation(&self) -> usize {
+    self.surface_generation.get()
+  }
+  pub(crate) fn register_surface_target(&self, target: &SurfaceTarget) {
+    self
+      .surface_targets
+      .borrow_mut()
+      .push(Rc::downgrade(target.state()));
+  }
+  #[track_caller]
+  pub fn surface_target(&self, size: SurfaceSize) -> SurfaceTarget {
+    self
+      .build_texture_2d(self.config.width, self.config.height)
+      .with_format(self.render_format())
+      .build_surface_target(size)
+  }
+  // A Depth32Float texture matching a surface target of the same size.
+  #[track_caller]
+  pub fn depth_target(&self, size: SurfaceSize) -> SurfaceTarget {
+    self
+      .build_depth_texture(self.config.width, self.config.height)
+      .build_surface_target(size)
+  }
+  #[track_caller]
+  pub fn ping_pong_texture(&self, size: SurfaceSize) -> PingPongTexture {
+    self
+      .build_texture_2d(self.config.width, self.config.height)
+      .with_format(self.render_format())
+      .build_ping_pong(size)
+  }
+  pub fn surface_bind_group(
+    &self,
+    build: impl Fn(&WGPUController) -> BindGroupWithLayout + 'static,
+  ) -> SurfaceBindGroup {
+    SurfaceBindGroup::new(self, build)
+  }
+  pub fn enable_resource_registry(&self) {
+    self
+      .registry
+      .borrow_mut()
+      .get_or_insert_with(ResourceRegistry::new);
+  }
+  pub fn resource_registry(&self) -> Option<Ref<'_, ResourceRegistry>> {
+    Ref::filter_map(self.registry.borrow(), Option::as_ref).ok()
+  }
+  pub fn resource_report(&self) -> Option<String> {
+    self
+      .resource_registry()
+      .map(|registry| registry.to_string())
+  }
+  #[track_caller]
+  fn register_resource(
+    &self,
+    label: Option<&str>,
+    size: u64,
+    usage: ResourceUsage,
+    lifetime_tracked: bool,
+  ) -> Option<ResourceToken> {
+    let location = Location::caller();
+    self.registry.borrow_mut().as_mut().and_then(|registry| {
+      registry.register(ResourceRecord {
+        label: label.map(str::to_owned),
+        size,
+        usage,
+        location,
+        created_frame: self.in_frame.get().then_some(self.frame_index.get()),
+        survived_frame: false,
+        lifetime_tracked,
+      })
+    })
+  }
+  #[track_caller]
+  pub(crate) fn register_buffer(
+    &self,
+    label: Option<&str>,
+    size: u64,
+    usage: BufferUsages,
+  ) -> Option<ResourceToken> {
+    self.register_resource(label, size, ResourceUsage::Buffer(usage), true)
+  }
+  #[track_caller]
+  pub(crate) fn register_texture(
+    &self,
+    descriptor: &TextureDescriptor,
+  ) -> Option<ResourceToken> {
+    self.register_resource(
+      descriptor.label,
+      texture_size(descriptor),
+      ResourceUsage::Texture(descriptor.usage),
+      true,
+    )
+  }
   pub fn create_encoder(&self) -> CommandEncoder {
     CommandEncoder::new(
       self
@@ -91,6 +360,19 @@ impl<'window> WGPUController<'window> {
     )
   }
   pub fn finish_encoder(&self, encoder: CommandEncoder) {
+    self.staging_belt.borrow_mut().finish();
+    self.queue.submit(std::iter::once(encoder.encoder.finish()));
+    self.staging_belt.borrow_mut().recall();
+  }
+  // Records and submits an encoder without finishing or recalling the staging
+  // belt, so it can run while an outer encoder still has uploads pending in
+  // the belt. Nothing recorded into it may use the belt.
+  pub(crate) fn submit_without_belt(
+    &self,
+    f: impl FnOnce(&mut CommandEncoder),
+  ) {
+    let mut encoder = self.create_encoder();
+    f(&mut encoder);
     self.queue.submit(std::iter::once(encoder.encoder.finish()));
   }
   pub fn shader(&self, source: ShaderModuleDescriptor<'_>) -> ShaderModule {
@@ -103,15 +385,111 @@ impl<'window> WGPUController<'window> {
   ) -> TextureBuilder<'w, 'w, 'w> {
     TextureBuilder::new_2d(self, width, height)
   }
+  pub fn build_storage_texture_2d<'w>(
+    &'w self,
+    width: u32,
+    height: u32,
+  ) -> TextureBuilder<'w, 'w, 'w> {
+    TextureBuilder::new_storage_2d(self, width, height)
+  }
+  pub fn build_depth_texture<'w>(
+    &'w self,
+    width: u32,
+    height: u32,
+  ) -> TextureBuilder<'w, 'w, 'w> {
+    TextureBuilder::new_depth(self, width, height)
+  }
+  pub fn build_depth_stencil_texture<'w>(
+    &'w self,
+    width: u32,
+    height: u32,
+  ) -> TextureBuilder<'w, 'w, 'w> {
+    TextureBuilder::new_depth_stencil(self, width, height)
+  }
+  pub fn build_texture_3d<'w>(
+    &'w self,
+    width: u32,
+    height: u32,
+    depth: u32,
+  ) -> TextureBuilder<'w, 'w, 'w> {
+    TextureBuilder::new_3d(self, width, height, depth)
+  }
+  pub fn build_texture_2d_array<'w>(
+    &'w self,
+    width: u32,
+    height: u32,
+    layers: u32,
+  ) -> TextureBuilder<'w, 'w, 'w> {
+    TextureBuilder::new_2d_array(self, width, height, layers)
+  }
+  pub fn build_texture_cube<'w>(
+    &'w self,
+    size: u32,
+  ) -> TextureBuilder<'w, 'w, 'w> {
+    TextureBuilder::new_cube(self, size)
+  }
+  pub fn build_texture_atlas(
+    &self,
+    page_size: u32,
+  ) -> TextureAtlasBuilder<'_, '_, 'window> {
+    TextureAtlasBuilder::new(self, page_size)
+  }
+  pub fn build_texture_from_image(
+    &self,
+    image: image::DynamicImage,
+  ) -> ImageTextureBuilder<'_, '_, 'window> {
+    ImageTextureBuilder::new(self, image)
+  }
+  pub fn build_texture_from_image_bytes(
+    &self,
+    bytes: &[u8],
+  ) -> Result<ImageTextureBuilder<'_, '_, 'window>, ImageLoadError> {
+    Ok(ImageTextureBuilder::new(self, decode_image_bytes(bytes)?))
+  }
+  pub fn build_texture_from_image_file(
+    &self,
+    path: impl AsRef<Path>,
+  ) -> Result<ImageTextureBuilder<'_, '_, 'window>, ImageLoadError> {
+    Ok(ImageTextureBuilder::new(self, decode_image_file(path)?))
+  }
+  #[track_caller]
+  pub fn load_texture(
+    &self,
+    path: impl AsRef<Path>,
+  ) -> Result<Texture, ImageLoadError> {
+    Ok(self.build_texture_from_image_file(path)?.build())
+  }
+  pub fn build_sampler(&self) -> SamplerBuilder<'_, '_, 'window> {
+    SamplerBuilder::new(self)
+  }
+  pub fn sampler(&self, preset: SamplerPreset) -> Sampler {
+    self
+      .samplers
+      .borrow_mut()
+      .entry(preset)
+      .or_insert_with(|| {
+        SamplerBuilder::from_preset(self, preset)
+          .with_label(&format!("{preset:?}"))
+          .build()
+      })
+      .clone()
+  }
   pub fn build_buffer<'a, 'w, T: NoUninit>(
     &'w self,
     contents: &'a [T],
   ) -> BufferBuilder<'a, 'w, 'w, 'window, T> {
     BufferBuilder::new(self, contents)
   }
+  #[track_caller]
   pub fn buffer<T: NoUninit>(&self, contents: T) -> Buffer<T> {
     BufferBuilder::new(self, &[contents]).build()
   }
+  #[track_caller]
+  pub fn uniform_buffer<T: NoUninit>(&self, contents: T) -> Buffer<T, Uniform> {
+    BufferBuilder::new(self, &[contents])
+      .with_usage_type()
+      .build()
+  }
   pub fn build_array_buffer_owned<T: NoUninit>(
     &self,
     contents: Vec<T>,
@@ -124,9 +502,45 @@ impl<'window> WGPUController<'window> {
   ) -> ArrayBufferBuilder<'a, 'a, 'a, 'a, T> {
     ArrayBufferBuilder::from_contents(self, contents)
   }
+  #[track_caller]
   pub fn array_buffer<T: NoUninit>(&self, contents: &[T]) -> ArrayBuffer<T> {
     ArrayBufferBuilder::from_contents(self, contents).build()
   }
+  #[track_caller]
+  pub fn vertex_array_buffer<T: NoUninit>(
+    &self,
+    contents: &[T],
+  ) -> ArrayBuffer<T, Vertex> {
+    ArrayBufferBuilder::from_contents(self, contents)
+      .with_usage_type()
+      .build()
+  }
+  #[track_caller]
+  pub fn index_array_buffer<T: NoUninit>(
+    &self,
+    contents: &[T],
+  ) -> ArrayBuffer<T, Index> {
+    ArrayBufferBuilder::from_contents(self, contents)
+      .with_usage_type()
+      .build()
+  }
+  #[track_caller]
+  pub fn storage_array_buffer<T: NoUninit>(
+    &self,
+    contents: &[T],
+  ) -> ArrayBuffer<T, Storage> {
+    ArrayBufferBuilder::from_contents(self, contents)
+      .with_usage_type()
+      .build()
+  }
+  #[track_caller]
+  pub fn mirrored_array_buffer<T: NoUninit, U: BufferUsage>(
+    &self,
+    contents: Vec<T>,
+  ) -> MirroredArrayBuffer<T, U> {
+    MirroredArrayBuffer::new(self, contents)
+  }
+  #[track_caller]
   pub fn zeroed_array_buffer<T: NoUninit + Zeroable>(
     &self,
     length: usize,
@@ -143,44 +557,69 @@ impl<'window> WGPUController<'window> {
   ) -> ArrayBufferBuilder<'_, '_, '_, '_, T> {
     ArrayBufferBuilder::empty(self, size)
   }
+  #[track_caller]
   pub fn empty_array_buffer<T: NoUninit + Zeroable>(
     &self,
     size: usize,
   ) -> ArrayBuffer<T> {
     self.build_empty_array_buffer(size).build()
   }
+  #[track_caller]
   pub fn vector_buffer<T: NoUninit>(&self) -> VectorBuffer<T> {
     VectorBufferBuilder::new(self).build()
   }
-  pub fn write_buffer<T: NoUninit>(
+  #[track_caller]
+  pub fn uniform_arena<T: NoUninit>(&self, capacity: usize) -> UniformArena<T> {
+    UniformArena::new(self, capacity)
+  }
+  #[track_caller]
+  pub fn append_buffer<T: NoUninit>(
+    &self,
+    capacity: usize,
+    vertex_count: u32,
+  ) -> AppendBuffer<T> {
+    AppendBuffer::new(self, capacity, vertex_count)
+  }
+  #[track_caller]
+  pub fn indirect_buffer<A: IndirectArgs>(
+    &self,
+    args: &[A],
+  ) -> ArrayBuffer<A, (Storage, Indirect)> {
+    ArrayBufferBuilder::from_contents(self, args)
+      .with_usage_type()
+      .build()
+  }
+  pub fn write_buffer<T: NoUninit, U: BufferUsage>(
     &self,
-    buffer: &Buffer<T>,
+    buffer: &Buffer<T, U>,
     data: impl IntoBufferData<T>,
-  ) -> &Self {
-    self.queue.write_buffer(
-      buffer,
-      0,
-      bytemuck::cast_slice(&[data.into_buffer_data()]),
-    );
-    self
+  ) -> Result<&Self, BufferWriteError> {
+    self.write_buffer_at(buffer, 0, &[data.into_buffer_data()])
   }
-  pub fn write_array_buffer<T: NoUninit>(
+  pub fn write_buffer_at<T: NoUninit, U: BufferUsage>(
     &self,
-    buffer: &ArrayBuffer<T>,
+    buffer: &Buffer<T, U>,
+    index: usize,
     data: &[T],
-  ) -> &Self {
+  ) -> Result<&Self, BufferWriteError> {
+    buffer.write(self, index, data)?;
+    Ok(self)
+  }
+  pub fn write_array_buffer<T: NoUninit, U: BufferUsage>(
+    &self,
+    buffer: &ArrayBuffer<T, U>,
+    data: &[T],
+  ) -> Result<&Self, BufferWriteError> {
     self.write_array_buffer_at(buffer, 0, data)
   }
-  pub fn write_array_buffer_at<T: NoUninit>(
+  pub fn write_array_buffer_at<T: NoUninit, U: BufferUsage>(
     &self,
-    buffer: &ArrayBuffer<T>,
-    index: u64,
+    buffer: &ArrayBuffer<T, U>,
+    index: usize,
     data: &[T],
-  ) -> &Self {
-    self
-      .queue
-      .write_buffer(buffer, index, bytemuck::cast_slice(data));
-    self
+  ) -> Result<&Self, BufferWriteError> {
+    buffer.write(self, index, data)?;
+    Ok(self)
   }
   pub fn build_render_pipeline(
     &self,
@@ -206,3 +645,11 @@ impl<'window> WGPUController<'window> {
     self.finish_encoder(encoder);
   }
 }
+
+#[cfg(test)]
+impl WGPUController<'static> {
+  // A headless controller on the fallback adapter.
+  pub(crate) fn for_tests() -> Self {
+    pollster::block_on(Self::new_headless(Features::empty(), true))
+  }
+}
@@ -1,10 +1,23 @@
 use std::ops::{Deref, DerefMut};
 
-use wgpu::{Color, TextureView};
+use bytemuck::NoUninit;
+use wgpu::{
+  BufferSize, Color, Extent3d, Features, ImageSubresourceRange, Origin3d,
+  TexelCopyBufferInfo, TexelCopyTextureInfo, Texture, TextureDimension,
+  TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+  COPY_BYTES_PER_ROW_ALIGNMENT,
+};
 
 use super::{
+  buffer::{
+    ArrayBuffer, ArrayBufferRange, Buffer, BufferUsage, BufferWriteError,
+    GpuBuffer, VectorBuffer,
+  },
   compute_pass::ComputePass,
+  controller::WGPUController,
+  mipmap::{generate_mipmaps, MipmapMethod},
   render_pass::{RenderPass, RenderPassBuilder},
+  texture::TextureCopyLayout,
 };
 
 pub struct CommandEncoder {
@@ -22,7 +35,7 @@ impl CommandEncoder {
   }
   pub fn simple_render_pass<'a>(
     &'a mut self,
-    view: &'a TextureView,
+    view: impl Into<&'a TextureView>,
   ) -> RenderPass<'a> {
     self
       .build_render_pass()
@@ -31,7 +44,7 @@ impl CommandEncoder {
   }
   pub fn clearing_render_pass<'a>(
     &'a mut self,
-    view: &'a TextureView,
+    view: impl Into<&'a TextureView>,
     color: Color,
   ) -> RenderPass<'a> {
     self
@@ -39,6 +52,284 @@ impl CommandEncoder {
       .add_clearing_color_attachment(view, color)
       .build()
   }
+  pub fn upload_buffer<T: NoUninit, U: BufferUsage>(
+    &mut self,
+    wgpu: &WGPUController,
+    buffer: &Buffer<T, U>,
+    index: usize,
+    data: &[T],
+  ) -> Result<&mut Self, BufferWriteError> {
+    buffer.upload(self, wgpu, index, data)?;
+    Ok(self)
+  }
+  pub fn upload_array_buffer<T: NoUninit, U: BufferUsage>(
+    &mut self,
+    wgpu: &WGPUController,
+    buffer: &ArrayBuffer<T, U>,
+    index: usize,
+    data: &[T],
+  ) -> Result<&mut Self, BufferWriteError> {
+    buffer.upload(self, wgpu, index, data)?;
+    Ok(self)
+  }
+  pub fn upload_vector_buffer<T: NoUninit, U: BufferUsage>(
+    &mut self,
+    wgpu: &WGPUController,
+    buffer: &VectorBuffer<T, U>,
+    index: usize,
+    data: &[T],
+  ) -> Result<&mut Self, BufferWriteError> {
+    buffer.upload(self, wgpu, index, data)?;
+    Ok(self)
+  }
+  #[track_caller]
+  pub fn copy_buffer_range<T: NoUninit, U: BufferUsage, V: BufferUsage>(
+    &mut self,
+    source: ArrayBufferRange<'_, T, U>,
+    destination: &ArrayBuffer<T, V>,
+    index: usize,
+  ) -> Result<&mut Self, BufferWriteError> {
+    destination.copy_from(self, source, index)?;
+    Ok(self)
+  }
+  #[track_caller]
+  pub fn clear_buffer_range<T: NoUninit, U: BufferUsage>(
+    &mut self,
+    range: ArrayBufferRange<'_, T, U>,
+  ) -> &mut Self {
+    range.assert_copy_aligned("clear");
+    self.clear_buffer(
+      range.buffer(),
+      range.byte_offset(),
+      Some(range.byte_size()),
+    );
+    self
+  }
+  pub fn copy_texture(
+    &mut self,
+    source: &Texture,
+    destination: &Texture,
+  ) -> &mut Self {
+    assert_eq!(
+      source.size(),
+      destination.size(),
+      "copy_texture needs textures of the same size, use copy_texture_region \
+      to copy between textures of different sizes"
+    );
+    self.copy_texture_region(
+      source,
+      Origin3d::ZERO,
+      destination,
+      Origin3d::ZERO,
+      source.size(),
+    )
+  }
+  pub fn copy_texture_region(
+    &mut self,
+    source: &Texture,
+    source_origin: Origin3d,
+    destination: &Texture,
+    destination_origin: Origin3d,
+    size: Extent3d,
+  ) -> &mut Self {
+    self.copy_texture_to_texture(
+      TexelCopyTextureInfo {
+        origin: source_origin,
+        ..source.as_image_copy()
+      },
+      TexelCopyTextureInfo {
+        origin: destination_origin,
+        ..destination.as_image_copy()
+      },
+      size,
+    );
+    self
+  }
+  // `source` holds the mip level tightly packed, with rows of
+  // `TextureCopyLayout::bytes_per_row` bytes. Rows that aren't aligned to
+  // COPY_BYTES_PER_ROW_ALIGNMENT are copied one at a time.
+  #[track_caller]
+  pub fn copy_buffer_to_texture_level<T: NoUninit, U: BufferUsage>(
+    &mut self,
+    source: &ArrayBuffer<T, U>,
+    destination: &Texture,
+    mip_level: u32,
+  ) -> &mut Self {
+    let layout = TextureCopyLayout::new(destination, mip_level);
+    assert!(
+      source.size() >= layout.size_in_bytes(),
+      "buffer of {} bytes is too small to hold mip level {mip_level} of {} \
+      bytes",
+      source.size(),
+      layout.size_in_bytes()
+    );
+    for (buffer_layout, origin, size) in layout.packed_copies(0) {
+      self.copy_buffer_to_texture(
+        TexelCopyBufferInfo {
+          buffer: source,
+          layout: buffer_layout,
+        },
+        TexelCopyTextureInfo {
+          mip_level,
+          origin,
+          ..destination.as_image_copy()
+        },
+        size,
+      );
+    }
+    self
+  }
+  // Leaves the mip level tightly packed in `destination`, the layout
+  // `copy_buffer_to_texture_level` takes.
+  #[track_caller]
+  pub fn copy_texture_level_to_buffer<T: NoUninit, U: BufferUsage>(
+    &mut self,
+    source: &Texture,
+    mip_level: u32,
+    destination: &ArrayBuffer<T, U>,
+  ) -> &mut Self {
+    let layout = TextureCopyLayout::new(source, mip_level);
+    assert!(
+      destination.size() >= layout.size_in_bytes(),
+      "buffer of {} bytes is too small to hold mip level {mip_level} of {} \
+      bytes",
+      destination.size(),
+      layout.size_in_bytes()
+    );
+    for (buffer_layout, origin, size) in layout.packed_copies(0) {
+      self.copy_texture_to_buffer(
+        TexelCopyTextureInfo {
+          mip_level,
+          origin,
+          ..source.as_image_copy()
+        },
+        TexelCopyBufferInfo {
+          buffer: destination,
+          layout: buffer_layout,
+        },
+        size,
+      );
+    }
+    self
+  }
+  // Uploads tightly packed texel data, padding its rows in the staging belt.
+  pub fn upload_texture<T: NoUninit>(
+    &mut self,
+    wgpu: &WGPUController,
+    destination: &Texture,
+    mip_level: u32,
+    data: &[T],
+  ) -> &mut Self {
+    let layout = TextureCopyLayout::new(destination, mip_level);
+    let bytes: &[u8] = bytemuck::cast_slice(data);
+    assert_eq!(
+      bytes.len() as u64,
+      layout.size_in_bytes(),
+      "texture data doesn't match the size of mip level {mip_level}"
+    );
+    let Some(size) = BufferSize::new(layout.padded_size_in_bytes()) else {
+      return self;
+    };
+    let mut belt = wgpu.staging_belt.borrow_mut();
+    let slice = belt.allocate(
+      size,
+      BufferSize::new(COPY_BYTES_PER_ROW_ALIGNMENT as u64).unwrap(),
+      &wgpu.device,
+    );
+    layout.pad_rows(bytes, &mut slice.get_mapped_range_mut());
+    self.copy_buffer_to_texture(
+      TexelCopyBufferInfo {
+        buffer: slice.buffer(),
+        layout: layout.buffer_layout(slice.offset()),
+      },
+      TexelCopyTextureInfo {
+        mip_level,
+        ..destination.as_image_copy()
+      },
+      layout.size,
+    );
+    self
+  }
+  #[track_caller]
+  pub fn clear_texture_to_zero(
+    &mut self,
+    wgpu: &WGPUController,
+    texture: &wgpu::Texture,
+  ) -> &mut Self {
+    self.clear_texture_to_color(wgpu, texture, Color::TRANSPARENT)
+  }
+  // Clears every mip level and layer of a texture. Zeroing goes through
+  // clear_texture when the device has CLEAR_TEXTURE, anything else runs a
+  // clearing render pass on each level and layer, so the texture has to be
+  // a renderable 2D one.
+  #[track_caller]
+  pub fn clear_texture_to_color(
+    &mut self,
+    wgpu: &WGPUController,
+    texture: &wgpu::Texture,
+    color: Color,
+  ) -> &mut Self {
+    if color == Color::TRANSPARENT
+      && wgpu.device.features().contains(Features::CLEAR_TEXTURE)
+    {
+      self.clear_texture(texture, &ImageSubresourceRange::default());
+      return self;
+    }
+    assert!(
+      texture.usage().contains(TextureUsages::RENDER_ATTACHMENT)
+        && texture.dimension() == TextureDimension::D2
+        && !texture.format().is_depth_stencil_format(),
+      "clearing a {:?} {:?} texture with usages {:?} needs a 2D color texture \
+      with RENDER_ATTACHMENT, or the CLEAR_TEXTURE feature to clear to zero",
+      texture.dimension(),
+      texture.format(),
+      texture.usage()
+    );
+    for mip_level in 0..texture.mip_level_count() {
+      for layer in 0..texture.depth_or_array_layers() {
+        let view = texture.create_view(&TextureViewDescriptor {
+          dimension: Some(TextureViewDimension::D2),
+          base_mip_level: mip_level,
+          mip_level_count: Some(1),
+          base_array_layer: layer,
+          array_layer_count: Some(1),
+          ..Default::default()
+        });
+        self.clearing_render_pass(&view, color);
+      }
+    }
+    self
+  }
+  // Fills the mip chain of a texture from its first level, using whichever
+  // method its usages and format allow.
+  pub fn generate_mipmaps(
+    &mut self,
+    wgpu: &WGPUController,
+    texture: &wgpu::Texture,
+  ) -> &mut Self {
+    if texture.mip_level_count() < 2 {
+      return self;
+    }
+    let Some(method) = MipmapMethod::for_texture(wgpu, texture) else {
+      panic!(
+        "can't generate mipmaps for a {:?} texture with usages {:?}, it needs \
+        RENDER_ATTACHMENT with a filterable format or STORAGE_BINDING with a \
+        storage format",
+        texture.format(),
+        texture.usage()
+      );
+    };
+    self.generate_mipmaps_with_method(wgpu, texture, method)
+  }
+  pub fn generate_mipmaps_with_method(
+    &mut self,
+    wgpu: &WGPUController,
+    texture: &wgpu::Texture,
+    method: MipmapMethod,
+  ) -> &mut Self {
+    generate_mipmaps(wgpu, self, texture, method);
+    self
+  }
   pub fn compute_pass(&mut self) -> ComputePass<'_> {
     ComputePass::new(self.begin_compute_pass(&wgpu::ComputePassDescriptor {
       label: None,
@@ -0,0 +1,153 @@
+use bytemuck::{NoUninit, Zeroable};
+use wgpu::{RenderPipeline, TextureFormat, TextureView};
+
+use super::{
+  buffer::{Buffer, Uniform},
+  controller::WGPUController,
+  encoder::CommandEncoder,
+  surface_target::{SurfaceBindGroup, SurfaceSize, SurfaceTarget},
+};
+
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+// How scene colors above 1 are brought into the surface's range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tonemap {
+  Reinhard,
+  #[default]
+  Aces,
+  // Only applies the exposure and clips whatever's left above 1.
+  Exposure,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrSettings {
+  pub tonemap: Tonemap,
+  pub exposure: f32,
+  pub dither: bool,
+}
+
+impl Default for HdrSettings {
+  fn default() -> Self {
+    Self {
+      tonemap: Tonemap::default(),
+      exposure: 1.,
+      dither: true,
+    }
+  }
+}
+
+impl HdrSettings {
+  pub fn with_tonemap(mut self, tonemap: Tonemap) -> Self {
+    self.tonemap = tonemap;
+    self
+  }
+  pub fn with_exposure(mut self, exposure: f32) -> Self {
+    self.exposure = exposure;
+    self
+  }
+  pub fn with_dither(mut self, dither: bool) -> Self {
+    self.dither = dither;
+    self
+  }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, NoUninit, Zeroable)]
+struct TonemapParams {
+  exposure: f32,
+  tonemap: u32,
+  dither: u32,
+  surface_is_srgb: u32,
+}
+
+// The scene target sketches render into in HDR mode, and the pass that
+// tonemaps it onto the surface.
+pub(crate) struct HdrOutput {
+  settings: HdrSettings,
+  scene: SurfaceTarget,
+  params: Buffer<TonemapParams, Uniform>,
+  bind_group: SurfaceBindGroup,
+  pipeline: RenderPipeline,
+}
+
+impl HdrOutput {
+  pub(crate) fn new(wgpu: &WGPUController, settings: HdrSettings) -> Self {
+    let scene = wgpu
+      .build_texture_2d(wgpu.config.width, wgpu.config.height)
+      .with_format(HDR_FORMAT)
+      .with_label("hdr scene")
+      .build_surface_target(SurfaceSize::Full);
+    let params = wgpu.uniform_buffer(TonemapParams::zeroed());
+    let bind_group = wgpu.surface_bind_group({
+      let scene = scene.clone();
+      let params = params.clone();
+      move |wgpu| {
+        wgpu
+          .build_bind_group_with_layout()
+          .with_texture_entry(&scene.view())
+          .with_uniform_buffer_entry(&params)
+          .build()
+      }
+    });
+    let pipeline = wgpu
+      .build_render_pipeline()
+      .with_label("tonemap")
+      .add_bind_group_layout(bind_group.layout())
+      .with_texture_format(wgpu.config.format)
+      .build_with_shader(&wgpu.shader(wgpu::include_wgsl!("tonemap.wgsl")));
+    let output = Self {
+      settings,
+      scene,
+      params,
+      bind_group,
+      pipeline,
+    };
+    output.write_params(wgpu);
+    output
+  }
+  fn write_params(&self, wgpu: &WGPUController) {
+    wgpu
+      .write_buffer(
+        &self.params,
+        TonemapParams {
+          exposure: self.settings.exposure,
+          tonemap: match self.settings.tonemap {
+            Tonemap::Reinhard => 0,
+            Tonemap::Aces => 1,
+            Tonemap::Exposure => 2,
+          },
+          dither: self.settings.dither as u32,
+          surface_is_srgb: wgpu.config.format.is_srgb() as u32,
+        },
+      )
+      .unwrap();
+  }
+  pub(crate) fn settings(&self) -> HdrSettings {
+    self.settings
+  }
+  pub(crate) fn set_settings(
+    &mut self,
+    wgpu: &WGPUController,
+    settings: HdrSettings,
+  ) {
+    self.settings = settings;
+    self.write_params(wgpu);
+  }
+  pub(crate) fn scene_view(&self) -> TextureView {
+    self.scene.view()
+  }
+  pub(crate) fn tonemap(
+    &mut self,
+    wgpu: &WGPUController,
+    encoder: &mut CommandEncoder,
+    surface_view: &TextureView,
+  ) {
+    let bind_group = self.bind_group.get(wgpu);
+    encoder
+      .simple_render_pass(surface_view)
+      .with_pipeline(&self.pipeline)
+      .with_bind_group(0, bind_group)
+      .draw(0..3, 0..1);
+  }
+}
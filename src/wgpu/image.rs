@@ -0,0 +1,449 @@
+use std::{fmt::Display, path::Path};
+
+use ::image::{DynamicImage, ImageError, ImageFormat, Rgba32FImage, RgbaImage};
+use half::f16;
+use wgpu::{
+  Extent3d, TexelCopyBufferInfo, TexelCopyBufferLayout, TexelCopyTextureInfo,
+  TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+
+use super::{
+  buffer::read_staging,
+  controller::WGPUController,
+  texture::{Texture, TextureCopyLayout},
+};
+
+// How the color channels of an image are encoded. Color images are normally
+// sRGB, while images holding data such as normals or heights are linear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+  #[default]
+  Srgb,
+  Linear,
+}
+
+#[derive(Debug)]
+pub enum ImageLoadError {
+  Io(std::io::Error),
+  Decode(ImageError),
+}
+
+impl Display for ImageLoadError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ImageLoadError::Io(error) => write!(f, "couldn't read image: {error}"),
+      ImageLoadError::Decode(error) => {
+        write!(f, "couldn't decode image: {error}")
+      }
+    }
+  }
+}
+
+impl std::error::Error for ImageLoadError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      ImageLoadError::Io(error) => Some(error),
+      ImageLoadError::Decode(error) => Some(error),
+    }
+  }
+}
+
+impl From<std::io::Error> for ImageLoadError {
+  fn from(error: std::io::Error) -> Self {
+    ImageLoadError::Io(error)
+  }
+}
+
+impl From<ImageError> for ImageLoadError {
+  fn from(error: ImageError) -> Self {
+    ImageLoadError::Decode(error)
+  }
+}
+
+pub(crate) fn decode_image_file(
+  path: impl AsRef<Path>,
+) -> Result<DynamicImage, ImageLoadError> {
+  decode_image_bytes(&std::fs::read(path)?)
+}
+
+pub(crate) fn decode_image_bytes(
+  bytes: &[u8],
+) -> Result<DynamicImage, ImageLoadError> {
+  Ok(::image::load_from_memory(bytes)?)
+}
+
+pub struct ImageTextureBuilder<'s, 'w, 'window> {
+  wgpu: &'w WGPUController<'window>,
+  image: DynamicImage,
+  label: Option<&'s str>,
+  color_space: ColorSpace,
+  mipmaps: bool,
+  usage: TextureUsages,
+}
+
+impl<'s, 'w, 'window> ImageTextureBuilder<'s, 'w, 'window> {
+  pub fn new(wgpu: &'w WGPUController<'window>, image: DynamicImage) -> Self {
+    Self {
+      wgpu,
+      image,
+      label: None,
+      color_space: ColorSpace::Srgb,
+      mipmaps: true,
+      usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+    }
+  }
+  pub fn with_label(mut self, label: &'s str) -> Self {
+    self.label = Some(label);
+    self
+  }
+  pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+    self.color_space = color_space;
+    self
+  }
+  pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+    self.mipmaps = mipmaps;
+    self
+  }
+  // Extra usages on top of TEXTURE_BINDING and COPY_DST.
+  pub fn with_usage(mut self, usage: TextureUsages) -> Self {
+    self.usage |= usage;
+    self
+  }
+  // 8-bit images keep their precision in an 8-bit format, sRGB unless the
+  // image is linear, in which case grayscale images keep fewer channels. Deeper
+  // images are converted to linear half floats.
+  pub fn format(&self) -> TextureFormat {
+    match (&self.image, self.color_space) {
+      (
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_)
+        | DynamicImage::ImageRgb32F(_)
+        | DynamicImage::ImageRgba32F(_),
+        _,
+      ) => TextureFormat::Rgba16Float,
+      (DynamicImage::ImageLuma8(_), ColorSpace::Linear) => {
+        TextureFormat::R8Unorm
+      }
+      (DynamicImage::ImageLumaA8(_), ColorSpace::Linear) => {
+        TextureFormat::Rg8Unorm
+      }
+      (_, ColorSpace::Linear) => TextureFormat::Rgba8Unorm,
+      (_, ColorSpace::Srgb) => TextureFormat::Rgba8UnormSrgb,
+    }
+  }
+  fn texel_data(&self, format: TextureFormat) -> Vec<u8> {
+    match format {
+      TextureFormat::R8Unorm | TextureFormat::Rg8Unorm => {
+        self.image.as_bytes().to_vec()
+      }
+      TextureFormat::Rgba16Float => {
+        let mut pixels = self.image.to_rgba32f().into_raw();
+        if self.color_space == ColorSpace::Srgb {
+          for pixel in pixels.chunks_mut(4) {
+            pixel[..3].iter_mut().for_each(|c| *c = srgb_to_linear(*c));
+          }
+        }
+        let pixels: Vec<f16> = pixels.into_iter().map(f16::from_f32).collect();
+        bytemuck::cast_slice(&pixels).to_vec()
+      }
+      _ => self.image.to_rgba8().into_raw(),
+    }
+  }
+  #[track_caller]
+  pub fn build(self) -> Texture {
+    let format = self.format();
+    let size = wgpu::Extent3d {
+      width: self.image.width(),
+      height: self.image.height(),
+      depth_or_array_layers: 1,
+    };
+    let texture = Texture::new(
+      self.wgpu,
+      &TextureDescriptor {
+        label: self.label,
+        size,
+        mip_level_count: if self.mipmaps {
+          size.max_mips(TextureDimension::D2)
+        } else {
+          1
+        },
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: if self.mipmaps {
+          self.usage | TextureUsages::RENDER_ATTACHMENT
+        } else {
+          self.usage
+        },
+        view_formats: &[],
+      },
+    );
+    // Written through the queue rather than the staging belt, so textures can
+    // be built while another encoder has uploads pending.
+    let data = self.texel_data(format);
+    let layout = TextureCopyLayout::new(&texture, 0);
+    self.wgpu.queue.write_texture(
+      texture.as_image_copy(),
+      &data,
+      TexelCopyBufferLayout {
+        offset: 0,
+        bytes_per_row: Some(layout.bytes_per_row),
+        rows_per_image: Some(layout.rows_per_image),
+      },
+      layout.size,
+    );
+    self.wgpu.submit_without_belt(|encoder| {
+      encoder.generate_mipmaps(self.wgpu, &texture);
+    });
+    texture
+  }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+  if c <= 0.04045 {
+    c / 12.92
+  } else {
+    ((c + 0.055) / 1.055).powf(2.4)
+  }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+  if c <= 0.0031308 {
+    c * 12.92
+  } else {
+    1.055 * c.powf(1. / 2.4) - 0.055
+  }
+}
+
+#[derive(Debug)]
+pub enum ImageSaveError {
+  UnsupportedFormat(TextureFormat),
+  Io(std::io::Error),
+  Encode(ImageError),
+}
+
+impl Display for ImageSaveError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ImageSaveError::UnsupportedFormat(format) => {
+        write!(f, "can't convert {format:?} texels to an image")
+      }
+      ImageSaveError::Io(error) => write!(f, "couldn't write image: {error}"),
+      ImageSaveError::Encode(error) => {
+        write!(f, "couldn't encode image: {error}")
+      }
+    }
+  }
+}
+
+impl std::error::Error for ImageSaveError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      ImageSaveError::UnsupportedFormat(_) => None,
+      ImageSaveError::Io(error) => Some(error),
+      ImageSaveError::Encode(error) => Some(error),
+    }
+  }
+}
+
+impl From<std::io::Error> for ImageSaveError {
+  fn from(error: std::io::Error) -> Self {
+    ImageSaveError::Io(error)
+  }
+}
+
+impl From<ImageError> for ImageSaveError {
+  fn from(error: ImageError) -> Self {
+    ImageSaveError::Encode(error)
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Component {
+  Unorm8,
+  Snorm8,
+  Float16,
+  Float32,
+}
+
+fn texel_components(format: TextureFormat) -> Option<(Component, usize)> {
+  use TextureFormat::*;
+  Some(match format {
+    R8Unorm => (Component::Unorm8, 1),
+    Rg8Unorm => (Component::Unorm8, 2),
+    Rgba8Unorm | Rgba8UnormSrgb | Bgra8Unorm | Bgra8UnormSrgb => {
+      (Component::Unorm8, 4)
+    }
+    R8Snorm => (Component::Snorm8, 1),
+    Rg8Snorm => (Component::Snorm8, 2),
+    Rgba8Snorm => (Component::Snorm8, 4),
+    R16Float => (Component::Float16, 1),
+    Rg16Float => (Component::Float16, 2),
+    Rgba16Float => (Component::Float16, 4),
+    R32Float | Depth32Float => (Component::Float32, 1),
+    Rg32Float => (Component::Float32, 2),
+    Rgba32Float => (Component::Float32, 4),
+    _ => return None,
+  })
+}
+
+// The tightly packed texels of one mip level, as read back from the GPU. The
+// layers of array textures follow each other, so they're exported as one tall
+// image.
+#[derive(Debug, Clone)]
+pub struct TextureData {
+  pub size: Extent3d,
+  pub format: TextureFormat,
+  pub bytes: Vec<u8>,
+}
+
+impl TextureData {
+  pub(crate) fn read(
+    wgpu: &WGPUController,
+    texture: &wgpu::Texture,
+    mip_level: u32,
+  ) -> Self {
+    assert!(
+      texture.usage().contains(TextureUsages::COPY_SRC),
+      "textures need COPY_SRC usage to be read back"
+    );
+    let layout = TextureCopyLayout::new(texture, mip_level);
+    let bytes = read_staging(
+      wgpu,
+      layout.padded_size_in_bytes(),
+      |encoder, staging| {
+        encoder.copy_texture_to_buffer(
+          TexelCopyTextureInfo {
+            mip_level,
+            ..texture.as_image_copy()
+          },
+          TexelCopyBufferInfo {
+            buffer: staging,
+            layout: layout.buffer_layout(0),
+          },
+          layout.size,
+        );
+      },
+      |mapped| layout.unpad_rows(mapped),
+    );
+    Self {
+      size: layout.size,
+      format: texture.format(),
+      bytes,
+    }
+  }
+  fn image_height(&self) -> u32 {
+    self.size.height * self.size.depth_or_array_layers
+  }
+  // Decodes every texel to linear RGBA. Single channel formats are spread
+  // over RGB so they read as grayscale, and two channel formats fill red and
+  // green.
+  pub fn to_linear_rgba(&self) -> Result<Vec<[f32; 4]>, ImageSaveError> {
+    let (component, channels) = texel_components(self.format)
+      .ok_or(ImageSaveError::UnsupportedFormat(self.format))?;
+    let values: Vec<f32> = match component {
+      Component::Unorm8 => {
+        self.bytes.iter().map(|&b| b as f32 / 255.).collect()
+      }
+      Component::Snorm8 => self
+        .bytes
+        .iter()
+        .map(|&b| (b as i8 as f32 / 127.).max(-1.))
+        .collect(),
+      Component::Float16 => self
+        .bytes
+        .chunks_exact(2)
+        .map(|b| f16::from_le_bytes([b[0], b[1]]).to_f32())
+        .collect(),
+      Component::Float32 => self
+        .bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect(),
+    };
+    let bgra = matches!(
+      self.format,
+      TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+    );
+    Ok(
+      values
+        .chunks_exact(channels)
+        .map(|texel| {
+          let mut rgba = match *texel {
+            [v] => [v, v, v, 1.],
+            [r, g] => [r, g, 0., 1.],
+            [b, g, r, a] if bgra => [r, g, b, a],
+            [r, g, b, a] => [r, g, b, a],
+            _ => unreachable!(),
+          };
+          if self.format.is_srgb() {
+            rgba[..3].iter_mut().for_each(|c| *c = srgb_to_linear(*c));
+          }
+          rgba
+        })
+        .collect(),
+    )
+  }
+  // Quantizes to 8 bits per channel, sRGB encoding the color channels unless
+  // the linear values should be written as they are.
+  pub fn to_rgba8(
+    &self,
+    color_space: ColorSpace,
+  ) -> Result<Vec<u8>, ImageSaveError> {
+    let quantize = |c: f32| (c.clamp(0., 1.) * 255.).round() as u8;
+    Ok(
+      self
+        .to_linear_rgba()?
+        .into_iter()
+        .flat_map(|[r, g, b, a]| {
+          let [r, g, b] = [r, g, b].map(|c| match color_space {
+            ColorSpace::Srgb => linear_to_srgb(c),
+            ColorSpace::Linear => c,
+          });
+          [r, g, b, a].map(quantize)
+        })
+        .collect(),
+    )
+  }
+  pub fn save_png(
+    &self,
+    path: impl AsRef<Path>,
+    color_space: ColorSpace,
+  ) -> Result<(), ImageSaveError> {
+    let image = RgbaImage::from_raw(
+      self.size.width,
+      self.image_height(),
+      self.to_rgba8(color_space)?,
+    )
+    .expect("texel count doesn't match the texture size");
+    Ok(image.save_with_format(path, ImageFormat::Png)?)
+  }
+  pub fn save_exr(&self, path: impl AsRef<Path>) -> Result<(), ImageSaveError> {
+    let image = Rgba32FImage::from_raw(
+      self.size.width,
+      self.image_height(),
+      self.to_linear_rgba()?.into_flattened(),
+    )
+    .expect("texel count doesn't match the texture size");
+    Ok(image.save_with_format(path, ImageFormat::OpenExr)?)
+  }
+  // Portable float map: a short text header followed by little endian RGB
+  // floats, with rows running from bottom to top.
+  pub fn save_pfm(&self, path: impl AsRef<Path>) -> Result<(), ImageSaveError> {
+    let texels = self.to_linear_rgba()?;
+    let width = self.size.width as usize;
+    let mut file =
+      format!("PF\n{} {}\n-1.0\n", self.size.width, self.image_height())
+        .into_bytes();
+    for row in texels.chunks_exact(width.max(1)).rev() {
+      for [r, g, b, _] in row {
+        for c in [r, g, b] {
+          file.extend_from_slice(&c.to_le_bytes());
+        }
+      }
+    }
+    Ok(std::fs::write(path, file)?)
+  }
+}
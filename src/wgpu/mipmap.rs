@@ -0,0 +1,265 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use wgpu::{
+  BindingType, ComputePipeline, Extent3d, RenderPipeline, SamplerBindingType,
+  ShaderStages, StorageTextureAccess, TextureDimension, TextureFormat,
+  TextureFormatFeatureFlags, TextureSampleType, TextureUsages, TextureView,
+  TextureViewDescriptor, TextureViewDimension,
+};
+
+use crate::include_prefixed_wgsl;
+
+use super::{
+  bind::{BindGroupLayout, BindGroupLayoutEntryBuilder},
+  controller::WGPUController,
+  encoder::CommandEncoder,
+  sampler::SamplerPreset,
+};
+
+const WORKGROUP_SIZE: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MipmapMethod {
+  // Renders each level from the one above with a linear filter. Needs
+  // RENDER_ATTACHMENT usage and a filterable, renderable format.
+  Render,
+  // Averages 2x2 blocks in a compute shader. Needs STORAGE_BINDING usage and
+  // a format that can be written as a storage texture, which excludes sRGB.
+  // The GL backend can't bind two mip levels of one texture at once, so this
+  // only works on the other backends.
+  Compute,
+}
+
+impl MipmapMethod {
+  // Prefers rendering, since it works with sRGB formats and filters in the
+  // fixed function hardware.
+  pub fn for_texture(
+    wgpu: &WGPUController,
+    texture: &wgpu::Texture,
+  ) -> Option<Self> {
+    let features = texture
+      .format()
+      .guaranteed_format_features(wgpu.device.features());
+    let usage = texture.usage();
+    if usage.contains(TextureUsages::RENDER_ATTACHMENT)
+      && features
+        .allowed_usages
+        .contains(TextureUsages::RENDER_ATTACHMENT)
+      && features
+        .flags
+        .contains(TextureFormatFeatureFlags::FILTERABLE)
+    {
+      Some(MipmapMethod::Render)
+    } else if usage.contains(TextureUsages::STORAGE_BINDING)
+      && features
+        .flags
+        .contains(TextureFormatFeatureFlags::STORAGE_WRITE_ONLY)
+      && storage_format_name(texture.format()).is_some()
+    {
+      Some(MipmapMethod::Compute)
+    } else {
+      None
+    }
+  }
+}
+
+fn storage_format_name(format: TextureFormat) -> Option<&'static str> {
+  Some(match format {
+    TextureFormat::R32Float => "r32float",
+    TextureFormat::Rg32Float => "rg32float",
+    TextureFormat::Rgba8Unorm => "rgba8unorm",
+    TextureFormat::Rgba8Snorm => "rgba8snorm",
+    TextureFormat::Bgra8Unorm => "bgra8unorm",
+    TextureFormat::Rgba16Float => "rgba16float",
+    TextureFormat::Rgba32Float => "rgba32float",
+    _ => return None,
+  })
+}
+
+struct MipmapPipeline<P> {
+  layout: BindGroupLayout,
+  pipeline: P,
+}
+
+// Pipelines are built the first time a format is used and kept for the
+// lifetime of the controller, so generating mipmaps every frame only costs the
+// passes themselves.
+#[derive(Default)]
+pub(crate) struct MipmapCache {
+  render_pipelines:
+    RefCell<HashMap<TextureFormat, Rc<MipmapPipeline<RenderPipeline>>>>,
+  compute_pipelines:
+    RefCell<HashMap<TextureFormat, Rc<MipmapPipeline<ComputePipeline>>>>,
+}
+
+impl MipmapCache {
+  fn render_pipeline(
+    &self,
+    wgpu: &WGPUController,
+    format: TextureFormat,
+  ) -> Rc<MipmapPipeline<RenderPipeline>> {
+    let mut pipelines = self.render_pipelines.borrow_mut();
+    let pipeline = pipelines.entry(format).or_insert_with(|| {
+      let layout = wgpu
+        .build_bind_group_layout()
+        .with_label("mipmap render")
+        .with_entry(
+          BindGroupLayoutEntryBuilder::new()
+            .with_visibility(ShaderStages::FRAGMENT)
+            .with_ty(BindingType::Texture {
+              sample_type: TextureSampleType::Float { filterable: true },
+              view_dimension: TextureViewDimension::D2,
+              multisampled: false,
+            }),
+        )
+        .with_entry(
+          BindGroupLayoutEntryBuilder::new()
+            .with_visibility(ShaderStages::FRAGMENT)
+            .with_ty(BindingType::Sampler(SamplerBindingType::Filtering)),
+        )
+        .build();
+      let pipeline = wgpu
+        .build_render_pipeline()
+        .with_label("mipmap render")
+        .add_bind_group_layout(&layout)
+        .with_texture_format(format)
+        .build_with_shader(&wgpu.shader(wgpu::include_wgsl!("mipmap.wgsl")));
+      Rc::new(MipmapPipeline { layout, pipeline })
+    });
+    pipeline.clone()
+  }
+  fn compute_pipeline(
+    &self,
+    wgpu: &WGPUController,
+    format: TextureFormat,
+  ) -> Rc<MipmapPipeline<ComputePipeline>> {
+    let mut pipelines = self.compute_pipelines.borrow_mut();
+    let pipeline = pipelines.entry(format).or_insert_with(|| {
+      let format_name = storage_format_name(format).unwrap_or_else(|| {
+        panic!("{format:?} can't be used as a storage texture for mipmaps")
+      });
+      let layout = wgpu
+        .build_bind_group_layout()
+        .with_label("mipmap compute")
+        .with_entry(
+          BindGroupLayoutEntryBuilder::new()
+            .with_visibility(ShaderStages::COMPUTE)
+            .with_ty(BindingType::Texture {
+              sample_type: TextureSampleType::Float { filterable: false },
+              view_dimension: TextureViewDimension::D2,
+              multisampled: false,
+            }),
+        )
+        .with_entry(
+          BindGroupLayoutEntryBuilder::new()
+            .with_visibility(ShaderStages::COMPUTE)
+            .with_ty(BindingType::StorageTexture {
+              access: StorageTextureAccess::WriteOnly,
+              format,
+              view_dimension: TextureViewDimension::D2,
+            }),
+        )
+        .build();
+      let pipeline = wgpu
+        .build_compute_pipeline()
+        .with_label("mipmap compute")
+        .add_bind_group_layout(&layout)
+        .build_with_shader_entry_point(
+          &wgpu.shader(include_prefixed_wgsl!(
+            "mipmap_compute.wgsl",
+            format!(
+              "alias Destination = texture_storage_2d<{format_name}, write>;\n"
+            )
+          )),
+          Some("downsample"),
+        );
+      Rc::new(MipmapPipeline { layout, pipeline })
+    });
+    pipeline.clone()
+  }
+}
+
+fn mip_view(
+  texture: &wgpu::Texture,
+  layer: u32,
+  mip_level: u32,
+) -> TextureView {
+  texture.create_view(&TextureViewDescriptor {
+    label: Some("mipmap"),
+    dimension: Some(TextureViewDimension::D2),
+    base_mip_level: mip_level,
+    mip_level_count: Some(1),
+    base_array_layer: layer,
+    array_layer_count: Some(1),
+    ..Default::default()
+  })
+}
+
+// Fills every mip level after the first from the level above, separately for
+// each array layer.
+pub(crate) fn generate_mipmaps(
+  wgpu: &WGPUController,
+  encoder: &mut CommandEncoder,
+  texture: &wgpu::Texture,
+  method: MipmapMethod,
+) {
+  assert_eq!(
+    texture.dimension(),
+    TextureDimension::D2,
+    "mipmaps can only be generated for 2D textures"
+  );
+  if texture.mip_level_count() < 2 {
+    return;
+  }
+  match method {
+    MipmapMethod::Render => {
+      let pipeline = wgpu.mipmaps.render_pipeline(wgpu, texture.format());
+      let sampler = wgpu.sampler(SamplerPreset::LinearClamp);
+      for layer in 0..texture.depth_or_array_layers() {
+        for mip_level in 1..texture.mip_level_count() {
+          let source = mip_view(texture, layer, mip_level - 1);
+          let destination = mip_view(texture, layer, mip_level);
+          let bind_group = pipeline
+            .layout
+            .build_group(wgpu)
+            .with_texture_entry(&source)
+            .with_sampler_entry(&sampler)
+            .build();
+          encoder
+            .simple_render_pass(&destination)
+            .with_pipeline(&pipeline.pipeline)
+            .with_bind_group(0, &bind_group)
+            .draw(0..3, 0..1);
+        }
+      }
+    }
+    MipmapMethod::Compute => {
+      let pipeline = wgpu.mipmaps.compute_pipeline(wgpu, texture.format());
+      for layer in 0..texture.depth_or_array_layers() {
+        for mip_level in 1..texture.mip_level_count() {
+          let source = mip_view(texture, layer, mip_level - 1);
+          let destination = mip_view(texture, layer, mip_level);
+          let bind_group = pipeline
+            .layout
+            .build_group(wgpu)
+            .with_texture_entry(&source)
+            .with_texture_entry(&destination)
+            .build();
+          encoder
+            .compute_pass()
+            .with_pipeline(&pipeline.pipeline)
+            .with_bind_group(0, &bind_group)
+            .dispatch_for_size(
+              Extent3d {
+                depth_or_array_layers: 1,
+                ..texture
+                  .size()
+                  .mip_level_size(mip_level, texture.dimension())
+              },
+              [WORKGROUP_SIZE, WORKGROUP_SIZE, 1],
+            );
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,22 @@
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+struct VertexOutput {
+  @builtin(position) position: vec4f,
+  @location(0) uv: vec2f,
+}
+
+// A single triangle covering the whole target.
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> VertexOutput {
+  let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+  return VertexOutput(
+    vec4f(uv * vec2f(2., -2.) + vec2f(-1., 1.), 0., 1.),
+    uv
+  );
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4f {
+  return textureSample(source, source_sampler, in.uv);
+}
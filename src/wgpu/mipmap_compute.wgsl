@@ -0,0 +1,19 @@
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var destination: Destination;
+
+// Each texel is the average of the 2x2 block above it. Odd source sizes clamp
+// the block at the edge.
+@compute @workgroup_size(8, 8)
+fn downsample(@builtin(global_invocation_id) id: vec3u) {
+  let size = textureDimensions(destination);
+  if any(id.xy >= size) {
+    return;
+  }
+  let last = textureDimensions(source) - 1u;
+  let corner = id.xy * 2u;
+  let sum = textureLoad(source, min(corner, last), 0)
+    + textureLoad(source, min(corner + vec2u(1u, 0u), last), 0)
+    + textureLoad(source, min(corner + vec2u(0u, 1u), last), 0)
+    + textureLoad(source, min(corner + vec2u(1u, 1u), last), 0);
+  textureStore(destination, id.xy, sum * 0.25);
+}
@@ -1,8 +1,17 @@
+pub mod arena;
+pub mod atlas;
 pub mod bind;
 pub mod buffer;
 pub mod compute_pass;
 pub mod controller;
 pub mod encoder;
+pub mod hdr;
+pub mod image;
+pub mod mipmap;
+pub mod ping_pong;
 pub mod pipeline;
+pub mod registry;
 pub mod render_pass;
+pub mod sampler;
+pub mod surface_target;
 pub mod texture;
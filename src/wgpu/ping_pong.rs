@@ -0,0 +1,188 @@
+use wgpu::{BindGroup, TextureView};
+
+use super::{
+  bind::{BindGroupLayout, BindGroupWithLayout, BindGroupWithLayoutBuilder},
+  controller::WGPUController,
+  surface_target::SurfaceTarget,
+};
+
+pub struct PingPong<R> {
+  resources: [R; 2],
+  current: usize,
+}
+
+impl<R> PingPong<R> {
+  pub fn new(first: R, second: R) -> Self {
+    Self {
+      resources: [first, second],
+      current: 0,
+    }
+  }
+  pub fn from_fn(mut f: impl FnMut() -> R) -> Self {
+    Self::new(f(), f())
+  }
+  pub fn current(&self) -> &R {
+    &self.resources[self.current]
+  }
+  pub fn previous(&self) -> &R {
+    &self.resources[1 - self.current]
+  }
+  pub fn current_mut(&mut self) -> &mut R {
+    &mut self.resources[self.current]
+  }
+  pub fn previous_mut(&mut self) -> &mut R {
+    &mut self.resources[1 - self.current]
+  }
+  pub fn resources(&self) -> &[R; 2] {
+    &self.resources
+  }
+  pub fn resources_mut(&mut self) -> &mut [R; 2] {
+    &mut self.resources
+  }
+  pub fn parity(&self) -> usize {
+    self.current
+  }
+  pub fn swap(&mut self) {
+    self.current = 1 - self.current;
+  }
+  pub fn build_bind_group<'w, 'window>(
+    &self,
+    wgpu: &'w WGPUController<'window>,
+    describe: impl for<'r> Fn(
+      BindGroupWithLayoutBuilder<'r, 'r, 'r, 'w, 'window>,
+      &'r R,
+      &'r R,
+    )
+      -> BindGroupWithLayoutBuilder<'r, 'r, 'r, 'w, 'window>,
+  ) -> PingPongBindGroup {
+    let [first, second] = &self.resources;
+    let first_current =
+      describe(BindGroupWithLayoutBuilder::new(wgpu), second, first).build();
+    let second_current =
+      describe(BindGroupWithLayoutBuilder::new(wgpu), first, second)
+        .build_group_with_layout(&first_current.layout);
+    PingPongBindGroup {
+      layout: first_current.layout,
+      groups: [first_current.group, second_current],
+    }
+  }
+}
+
+// A bind group built for both orientations of a `PingPong`, sharing a single
+// layout. `describe` receives the previous resource and then the current one.
+pub struct PingPongBindGroup {
+  pub layout: BindGroupLayout,
+  groups: [BindGroup; 2],
+}
+
+impl PingPongBindGroup {
+  pub fn get<R>(&self, ping_pong: &PingPong<R>) -> &BindGroup {
+    &self.groups[ping_pong.parity()]
+  }
+  pub fn groups(&self) -> &[BindGroup; 2] {
+    &self.groups
+  }
+}
+
+type BuildTextureBindGroup =
+  dyn Fn(&WGPUController, &TextureView, &TextureView) -> BindGroupWithLayout;
+
+// A pair of identical surface targets for feedback effects, where each frame
+// reads the texture written by the previous one. Call `swap` once per frame.
+pub struct PingPongTexture {
+  targets: PingPong<SurfaceTarget>,
+}
+
+impl PingPongTexture {
+  pub fn new(first: SurfaceTarget, second: SurfaceTarget) -> Self {
+    Self {
+      targets: PingPong::new(first, second),
+    }
+  }
+  pub fn current(&self) -> &SurfaceTarget {
+    self.targets.current()
+  }
+  pub fn previous(&self) -> &SurfaceTarget {
+    self.targets.previous()
+  }
+  pub fn current_view(&self) -> TextureView {
+    self.current().view()
+  }
+  pub fn previous_view(&self) -> TextureView {
+    self.previous().view()
+  }
+  pub fn parity(&self) -> usize {
+    self.targets.parity()
+  }
+  pub fn swap(&mut self) {
+    self.targets.swap();
+  }
+  pub fn width(&self) -> u32 {
+    self.current().width()
+  }
+  pub fn height(&self) -> u32 {
+    self.current().height()
+  }
+  // Like `PingPong::build_bind_group`, `build` receives the view to read from
+  // and then the view to write to. It's kept so both groups can be rebuilt
+  // when the surface is resized. Identical layouts are compatible, so the
+  // second group's layout is dropped.
+  pub fn build_bind_group<F>(
+    &self,
+    wgpu: &WGPUController,
+    build: F,
+  ) -> PingPongTextureBindGroup
+  where
+    F: Fn(&WGPUController, &TextureView, &TextureView) -> BindGroupWithLayout
+      + 'static,
+  {
+    let targets = self.targets.resources().clone();
+    PingPongTextureBindGroup {
+      bind_group: build_texture_bind_group(wgpu, &targets, &build),
+      build: Box::new(build),
+      targets,
+      generation: wgpu.surface_generation(),
+    }
+  }
+}
+
+fn build_texture_bind_group(
+  wgpu: &WGPUController,
+  targets: &[SurfaceTarget; 2],
+  build: &BuildTextureBindGroup,
+) -> PingPongBindGroup {
+  let [first, second] = targets.each_ref().map(SurfaceTarget::view);
+  let first_current = build(wgpu, &second, &first);
+  let second_current = build(wgpu, &first, &second);
+  PingPongBindGroup {
+    layout: first_current.layout,
+    groups: [first_current.group, second_current.group],
+  }
+}
+
+// The bind groups for both orientations of a `PingPongTexture`, rebuilt the
+// first time they're used after a resize.
+pub struct PingPongTextureBindGroup {
+  build: Box<BuildTextureBindGroup>,
+  targets: [SurfaceTarget; 2],
+  bind_group: PingPongBindGroup,
+  generation: usize,
+}
+
+impl PingPongTextureBindGroup {
+  pub fn layout(&self) -> &BindGroupLayout {
+    &self.bind_group.layout
+  }
+  pub fn get(
+    &mut self,
+    wgpu: &WGPUController,
+    textures: &PingPongTexture,
+  ) -> &BindGroup {
+    if self.generation != wgpu.surface_generation() {
+      self.bind_group =
+        build_texture_bind_group(wgpu, &self.targets, &self.build);
+      self.generation = wgpu.surface_generation();
+    }
+    &self.bind_group.groups()[textures.parity()]
+  }
+}
@@ -1,11 +1,12 @@
 use std::num::NonZero;
 
 use wgpu::{
-  BindGroupLayout, BlendState, ColorTargetState, ComputePipeline,
+  BindGroupLayout, BlendComponent, BlendFactor, BlendOperation, BlendState,
+  ColorTargetState, CompareFunction, ComputePipeline, DepthBiasState,
   DepthStencilState, FragmentState, MultisampleState,
   PipelineCompilationOptions, PrimitiveState, RenderPipeline,
-  RenderPipelineDescriptor, ShaderModule, TextureFormat, VertexBufferLayout,
-  VertexState,
+  RenderPipelineDescriptor, ShaderModule, StencilFaceState, StencilOperation,
+  StencilState, TextureFormat, VertexBufferLayout, VertexState,
 };
 
 use super::controller::WGPUController;
@@ -75,6 +76,104 @@ impl<'w, 'window, 's, 'v, 'b, 'p, 'shader>
     self.depth_stencil = Some(depth_stencil);
     self
   }
+  // The depth and stencil helpers below fill in whichever part of the state
+  // they're about, so depth testing and stencil masking can be combined.
+  fn depth_stencil_mut(
+    &mut self,
+    format: TextureFormat,
+  ) -> &mut DepthStencilState {
+    let depth_stencil =
+      self.depth_stencil.get_or_insert_with(|| DepthStencilState {
+        format,
+        depth_write_enabled: false,
+        depth_compare: CompareFunction::Always,
+        stencil: StencilState::default(),
+        bias: DepthBiasState::default(),
+      });
+    depth_stencil.format = format;
+    depth_stencil
+  }
+  pub fn with_depth_test(
+    mut self,
+    format: TextureFormat,
+    compare: CompareFunction,
+    write_enabled: bool,
+  ) -> Self {
+    let depth_stencil = self.depth_stencil_mut(format);
+    depth_stencil.depth_compare = compare;
+    depth_stencil.depth_write_enabled = write_enabled;
+    self
+  }
+  // Nearer fragments win, for depth cleared to 1.
+  pub fn with_standard_depth(self, format: TextureFormat) -> Self {
+    self.with_depth_test(format, CompareFunction::Less, true)
+  }
+  // Reversed-Z, which is more precise with float depth cleared to 0.
+  pub fn with_reverse_depth(self, format: TextureFormat) -> Self {
+    self.with_depth_test(format, CompareFunction::Greater, true)
+  }
+  // Tests against depth written by opaque geometry without changing it, as
+  // transparent geometry should.
+  pub fn with_read_only_depth(self, format: TextureFormat) -> Self {
+    self.with_depth_test(format, CompareFunction::LessEqual, false)
+  }
+  pub fn with_depth_bias(
+    mut self,
+    format: TextureFormat,
+    bias: DepthBiasState,
+  ) -> Self {
+    self.depth_stencil_mut(format).bias = bias;
+    self
+  }
+  // Applies `face` to both front and back faces.
+  pub fn with_stencil(
+    mut self,
+    format: TextureFormat,
+    face: StencilFaceState,
+    read_mask: u32,
+    write_mask: u32,
+  ) -> Self {
+    self.depth_stencil_mut(format).stencil = StencilState {
+      front: face,
+      back: face,
+      read_mask,
+      write_mask,
+    };
+    self
+  }
+  // Writes the pass's stencil reference wherever this pipeline draws.
+  pub fn with_stencil_write(self, format: TextureFormat) -> Self {
+    self.with_stencil(
+      format,
+      StencilFaceState {
+        compare: CompareFunction::Always,
+        fail_op: StencilOperation::Keep,
+        depth_fail_op: StencilOperation::Keep,
+        pass_op: StencilOperation::Replace,
+      },
+      !0,
+      !0,
+    )
+  }
+  // Only draws where the stencil matches the pass's stencil reference
+  // according to `compare`, without changing it.
+  pub fn with_stencil_mask(
+    self,
+    format: TextureFormat,
+    compare: CompareFunction,
+  ) -> Self {
+    self.with_stencil(
+      format,
+      StencilFaceState {
+        compare,
+        fail_op: StencilOperation::Keep,
+        depth_fail_op: StencilOperation::Keep,
+        pass_op: StencilOperation::Keep,
+      },
+      !0,
+      0,
+    )
+  }
   pub fn with_multisample(mut self, multisample: MultisampleState) -> Self {
     self.multisample = Some(multisample);
     self
@@ -87,6 +186,19 @@ impl<'w, 'window, 's, 'v, 'b, 'p, 'shader>
     self.blend_state = Some(blend_state);
     self
   }
+  // Adds each fragment onto what's already there, for accumulating points or
+  // light. Best used with an HDR target so the sum doesn't clip.
+  pub fn with_additive_blending(self) -> Self {
+    let component = BlendComponent {
+      src_factor: BlendFactor::One,
+      dst_factor: BlendFactor::One,
+      operation: BlendOperation::Add,
+    };
+    self.with_blend_state(BlendState {
+      color: component,
+      alpha: component,
+    })
+  }
   pub fn with_texture_format(mut self, texture_format: TextureFormat) -> Self {
     self.texture_format = Some(texture_format);
     self
@@ -133,7 +245,9 @@ impl<'w, 'window, 's, 'v, 'b, 'p, 'shader>
     fragment_entry_point: Option<Option<&'fs str>>,
   ) -> RenderPipeline {
     let fragment_targets = &[Some(ColorTargetState {
-      format: self.texture_format.unwrap_or(self.wgpu.config.format),
+      format: self
+        .texture_format
+        .unwrap_or_else(|| self.wgpu.render_format()),
       blend: self.blend_state,
       write_mask: wgpu::ColorWrites::ALL,
     })];
@@ -0,0 +1,175 @@
+use std::{
+  fmt::Display,
+  panic::Location,
+  sync::{Arc, Weak},
+};
+
+use wgpu::{BufferUsages, TextureDescriptor, TextureUsages};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceUsage {
+  Buffer(BufferUsages),
+  Texture(TextureUsages),
+}
+
+#[derive(Debug, Clone)]
+pub struct ResourceRecord {
+  pub label: Option<String>,
+  pub size: u64,
+  pub usage: ResourceUsage,
+  pub location: &'static Location<'static>,
+  pub created_frame: Option<usize>,
+  pub survived_frame: bool,
+  pub lifetime_tracked: bool,
+}
+
+// Held by the wrapper of a registered resource. Once every clone of the token
+// has been dropped, the registry considers the resource dropped.
+#[derive(Debug, Clone)]
+pub struct ResourceToken(#[allow(dead_code)] Arc<()>);
+
+struct Entry {
+  record: ResourceRecord,
+  alive: Option<Weak<()>>,
+}
+
+impl Entry {
+  fn is_alive(&self) -> bool {
+    self
+      .alive
+      .as_ref()
+      .is_none_or(|alive| alive.strong_count() > 0)
+  }
+}
+
+#[derive(Default)]
+pub struct ResourceRegistry {
+  entries: Vec<Entry>,
+  dropped_count: usize,
+  dropped_bytes: u64,
+}
+
+impl ResourceRegistry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+  pub(crate) fn register(
+    &mut self,
+    record: ResourceRecord,
+  ) -> Option<ResourceToken> {
+    let token = record.lifetime_tracked.then(|| ResourceToken(Arc::new(())));
+    self.entries.push(Entry {
+      record,
+      alive: token.as_ref().map(|token| Arc::downgrade(&token.0)),
+    });
+    token
+  }
+  pub(crate) fn end_frame(&mut self, frame_index: usize) {
+    self.prune();
+    for entry in self.entries.iter_mut() {
+      if entry.alive.is_some()
+        && entry.record.created_frame == Some(frame_index)
+      {
+        entry.record.survived_frame = true;
+      }
+    }
+  }
+  fn prune(&mut self) {
+    let (alive, dropped): (Vec<_>, Vec<_>) = std::mem::take(&mut self.entries)
+      .into_iter()
+      .partition(Entry::is_alive);
+    self.entries = alive;
+    self.dropped_count += dropped.len();
+    self.dropped_bytes +=
+      dropped.iter().map(|entry| entry.record.size).sum::<u64>();
+  }
+  pub fn live_resources(&self) -> Vec<ResourceRecord> {
+    self
+      .entries
+      .iter()
+      .filter(|entry| entry.is_alive())
+      .map(|entry| entry.record.clone())
+      .collect()
+  }
+  pub fn frame_survivors(&self) -> Vec<ResourceRecord> {
+    self
+      .live_resources()
+      .into_iter()
+      .filter(|record| record.survived_frame)
+      .collect()
+  }
+  pub fn total_bytes(&self) -> u64 {
+    self.live_resources().iter().map(|record| record.size).sum()
+  }
+  pub fn buffer_bytes(&self) -> u64 {
+    self
+      .live_resources()
+      .iter()
+      .filter(|record| matches!(record.usage, ResourceUsage::Buffer(_)))
+      .map(|record| record.size)
+      .sum()
+  }
+  pub fn texture_bytes(&self) -> u64 {
+    self
+      .live_resources()
+      .iter()
+      .filter(|record| matches!(record.usage, ResourceUsage::Texture(_)))
+      .map(|record| record.size)
+      .sum()
+  }
+  pub fn dropped_count(&self) -> usize {
+    self.dropped_count
+  }
+  pub fn dropped_bytes(&self) -> u64 {
+    self.dropped_bytes
+  }
+}
+
+impl Display for ResourceRegistry {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let live = self.live_resources();
+    writeln!(
+      f,
+      "{} live GPU resources, {} bytes ({} in buffers, {} in textures)",
+      live.len(),
+      self.total_bytes(),
+      self.buffer_bytes(),
+      self.texture_bytes()
+    )?;
+    writeln!(
+      f,
+      "{} resources dropped, {} bytes freed",
+      self.dropped_count, self.dropped_bytes
+    )?;
+    for record in &live {
+      writeln!(
+        f,
+        "  {:>12} bytes  {:<24} {:?} at {}{}{}",
+        record.size,
+        record.label.as_deref().unwrap_or("<unlabeled>"),
+        record.usage,
+        record.location,
+        record
+          .created_frame
+          .map(|frame| format!(", created in frame {frame}"))
+          .unwrap_or_default(),
+        if !record.lifetime_tracked {
+          " (lifetime untracked)"
+        } else if record.survived_frame {
+          " (outlived its frame)"
+        } else {
+          ""
+        }
+      )?;
+    }
+    Ok(())
+  }
+}
+
+pub(crate) fn texture_size(descriptor: &TextureDescriptor) -> u64 {
+  (0..descriptor.mip_level_count)
+    .filter_map(|level| descriptor.mip_level_size(level))
+    .map(|size| descriptor.format.theoretical_memory_footprint(size))
+    .sum::<u64>()
+    * descriptor.sample_count as u64
+}
@@ -1,12 +1,17 @@
 use std::ops::{Deref, DerefMut, Range};
 
 use wgpu::{
-  BufferSlice, Color, QuerySet, RenderPassColorAttachment,
-  RenderPassDepthStencilAttachment, RenderPassTimestampWrites, TextureView,
+  BufferSlice, Color, Features, LoadOp, Operations, QuerySet,
+  RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+  RenderPassTimestampWrites, StoreOp, TextureView,
 };
 
 use super::{
-  buffer::{ArrayBuffer, Buffer, IntoVertexBufferData},
+  buffer::{
+    ArrayBuffer, ArrayBufferRange, DrawIndexedIndirectArgs, DrawIndirectArgs,
+    GpuBuffer, Index, Indirect, IntoVertexBufferData, Supports,
+  },
+  controller::WGPUController,
   encoder::CommandEncoder,
 };
 
@@ -45,10 +50,10 @@ impl<'e, 's, 'query: 'e, 'tex: 'e, 'desc>
   }
   pub fn add_simple_color_attachment<'v: 'e + 'tex>(
     self,
-    view: &'v TextureView,
+    view: impl Into<&'v TextureView>,
   ) -> Self {
     self.add_color_attachment(Some(wgpu::RenderPassColorAttachment {
-      view,
+      view: view.into(),
       resolve_target: None,
       ops: wgpu::Operations {
         load: wgpu::LoadOp::Load,
@@ -59,11 +64,11 @@ impl<'e, 's, 'query: 'e, 'tex: 'e, 'desc>
   }
   pub fn add_clearing_color_attachment<'v: 'e + 'tex>(
     self,
-    view: &'v TextureView,
+    view: impl Into<&'v TextureView>,
     color: Color,
   ) -> Self {
     self.add_color_attachment(Some(wgpu::RenderPassColorAttachment {
-      view,
+      view: view.into(),
       resolve_target: None,
       ops: wgpu::Operations {
         load: wgpu::LoadOp::Clear(color),
@@ -79,6 +84,81 @@ impl<'e, 's, 'query: 'e, 'tex: 'e, 'desc>
     self.depth_stencil_attachment = Some(attachment);
     self
   }
+  pub fn add_depth_attachment<'v: 'e + 'tex>(
+    self,
+    view: impl Into<&'v TextureView>,
+    clear_value: f32,
+  ) -> Self {
+    self.with_depth_stencil_attachment(RenderPassDepthStencilAttachment {
+      view: view.into(),
+      depth_ops: Some(Operations {
+        load: LoadOp::Clear(clear_value),
+        store: StoreOp::Store,
+      }),
+      stencil_ops: None,
+    })
+  }
+  // Keeps the depth written by earlier passes.
+  pub fn add_loaded_depth_attachment<'v: 'e + 'tex>(
+    self,
+    view: impl Into<&'v TextureView>,
+  ) -> Self {
+    self.with_depth_stencil_attachment(RenderPassDepthStencilAttachment {
+      view: view.into(),
+      depth_ops: Some(Operations {
+        load: LoadOp::Load,
+        store: StoreOp::Store,
+      }),
+      stencil_ops: None,
+    })
+  }
+  pub fn add_depth_stencil_attachment<'v: 'e + 'tex>(
+    self,
+    view: impl Into<&'v TextureView>,
+    depth_clear_value: f32,
+    stencil_clear_value: u32,
+  ) -> Self {
+    self.with_depth_stencil_attachment(RenderPassDepthStencilAttachment {
+      view: view.into(),
+      depth_ops: Some(Operations {
+        load: LoadOp::Clear(depth_clear_value),
+        store: StoreOp::Store,
+      }),
+      stencil_ops: Some(Operations {
+        load: LoadOp::Clear(stencil_clear_value),
+        store: StoreOp::Store,
+      }),
+    })
+  }
+  // Only the stencil aspect is cleared and written, so the depth aspect of a
+  // combined format is left read only.
+  pub fn add_stencil_attachment<'v: 'e + 'tex>(
+    self,
+    view: impl Into<&'v TextureView>,
+    clear_value: u32,
+  ) -> Self {
+    self.with_depth_stencil_attachment(RenderPassDepthStencilAttachment {
+      view: view.into(),
+      depth_ops: None,
+      stencil_ops: Some(Operations {
+        load: LoadOp::Clear(clear_value),
+        store: StoreOp::Store,
+      }),
+    })
+  }
+  pub fn add_loaded_stencil_attachment<'v: 'e + 'tex>(
+    self,
+    view: impl Into<&'v TextureView>,
+  ) -> Self {
+    self.with_depth_stencil_attachment(RenderPassDepthStencilAttachment {
+      view: view.into(),
+      depth_ops: None,
+      stencil_ops: Some(Operations {
+        load: LoadOp::Load,
+        store: StoreOp::Store,
+      }),
+    })
+  }
   pub fn with_optional_depth_stencil_attachment(
     mut self,
     attachment: Option<RenderPassDepthStencilAttachment<'tex>>,
@@ -110,6 +190,18 @@ impl<'e, 's, 'query: 'e, 'tex: 'e, 'desc>
   }
 }
 
+#[track_caller]
+fn check_indirect_count(wgpu: &WGPUController) {
+  assert!(
+    wgpu
+      .device
+      .features()
+      .contains(Features::MULTI_DRAW_INDIRECT_COUNT),
+    "indirect draws with a count buffer need the MULTI_DRAW_INDIRECT_COUNT \
+    feature, which the device wasn't created with"
+  );
+}
+
 pub struct RenderPass<'p> {
   pass: wgpu::RenderPass<'p>,
 }
@@ -210,6 +302,106 @@ impl<'p> RenderPass<'p> {
     self.pass.draw(vertices, instances);
     self
   }
+  pub fn draw_indirect<U: Supports<Indirect>>(
+    mut self,
+    args: &ArrayBuffer<DrawIndirectArgs, U>,
+    index: usize,
+  ) -> Self {
+    let range = args.range(index..index + 1);
+    self.pass.draw_indirect(args, range.byte_offset());
+    self
+  }
+  pub fn draw_indexed_indirect<U: Supports<Indirect>>(
+    mut self,
+    args: &ArrayBuffer<DrawIndexedIndirectArgs, U>,
+    index: usize,
+  ) -> Self {
+    let range = args.range(index..index + 1);
+    self.pass.draw_indexed_indirect(args, range.byte_offset());
+    self
+  }
+  pub fn draw_indexed_indirect_u16<U: Supports<Indirect>>(
+    mut self,
+    index_data: impl IntoIndexBufferDataU16<'p>,
+    args: &ArrayBuffer<DrawIndexedIndirectArgs, U>,
+    index: usize,
+  ) -> Self {
+    self.set_index_buffer_u16(index_data);
+    self.draw_indexed_indirect(args, index)
+  }
+  pub fn draw_indexed_indirect_u32<U: Supports<Indirect>>(
+    mut self,
+    index_data: impl IntoIndexBufferDataU32<'p>,
+    args: &ArrayBuffer<DrawIndexedIndirectArgs, U>,
+    index: usize,
+  ) -> Self {
+    self.set_index_buffer_u32(index_data);
+    self.draw_indexed_indirect(args, index)
+  }
+  pub fn multi_draw_indirect<U: Supports<Indirect>>(
+    mut self,
+    args: ArrayBufferRange<'_, DrawIndirectArgs, U>,
+  ) -> Self {
+    self.pass.multi_draw_indirect(
+      args.buffer(),
+      args.byte_offset(),
+      args.len() as u32,
+    );
+    self
+  }
+  pub fn multi_draw_indexed_indirect<U: Supports<Indirect>>(
+    mut self,
+    args: ArrayBufferRange<'_, DrawIndexedIndirectArgs, U>,
+  ) -> Self {
+    self.pass.multi_draw_indexed_indirect(
+      args.buffer(),
+      args.byte_offset(),
+      args.len() as u32,
+    );
+    self
+  }
+  #[track_caller]
+  pub fn multi_draw_indirect_count<
+    U: Supports<Indirect>,
+    C: Supports<Indirect>,
+  >(
+    mut self,
+    wgpu: &WGPUController,
+    args: ArrayBufferRange<'_, DrawIndirectArgs, U>,
+    count: &ArrayBuffer<u32, C>,
+    count_index: usize,
+  ) -> Self {
+    check_indirect_count(wgpu);
+    self.pass.multi_draw_indirect_count(
+      args.buffer(),
+      args.byte_offset(),
+      count,
+      count.range(count_index..count_index + 1).byte_offset(),
+      args.len() as u32,
+    );
+    self
+  }
+  #[track_caller]
+  pub fn multi_draw_indexed_indirect_count<
+    U: Supports<Indirect>,
+    C: Supports<Indirect>,
+  >(
+    mut self,
+    wgpu: &WGPUController,
+    args: ArrayBufferRange<'_, DrawIndexedIndirectArgs, U>,
+    count: &ArrayBuffer<u32, C>,
+    count_index: usize,
+  ) -> Self {
+    check_indirect_count(wgpu);
+    self.pass.multi_draw_indexed_indirect_count(
+      args.buffer(),
+      args.byte_offset(),
+      count,
+      count.range(count_index..count_index + 1).byte_offset(),
+      args.len() as u32,
+    );
+    self
+  }
 }
 
 pub trait IntoIndexBufferDataU16<'s> {
@@ -220,14 +412,18 @@ impl<'s> IntoIndexBufferDataU16<'s> for BufferSlice<'s> {
     self
   }
 }
-impl<'s, 'b: 's> IntoIndexBufferDataU16<'s> for &'b Buffer<u16> {
+impl<'s, 'b: 's, B: GpuBuffer<Element = u16, Usage: Supports<Index>>>
+  IntoIndexBufferDataU16<'s> for &'b B
+{
   fn into_index_buffer_data_u16(self) -> BufferSlice<'s> {
-    self.slice(..)
+    self.slice_elements(..self.len())
   }
 }
-impl<'s, 'b: 's> IntoIndexBufferDataU16<'s> for &'b ArrayBuffer<u16> {
+impl<'s, 'b: 's, U: Supports<Index>> IntoIndexBufferDataU16<'s>
+  for ArrayBufferRange<'b, u16, U>
+{
   fn into_index_buffer_data_u16(self) -> BufferSlice<'s> {
-    self.slice(..)
+    self.slice()
   }
 }
 pub trait IntoIndexBufferDataU32<'s> {
@@ -238,14 +434,18 @@ impl<'s> IntoIndexBufferDataU32<'s> for BufferSlice<'s> {
     self
   }
 }
-impl<'s, 'b: 's> IntoIndexBufferDataU32<'s> for &'b Buffer<u32> {
+impl<'s, 'b: 's, B: GpuBuffer<Element = u32, Usage: Supports<Index>>>
+  IntoIndexBufferDataU32<'s> for &'b B
+{
   fn into_index_buffer_data_u32(self) -> BufferSlice<'s> {
-    self.slice(..)
+    self.slice_elements(..self.len())
   }
 }
-impl<'s, 'b: 's> IntoIndexBufferDataU32<'s> for &'b ArrayBuffer<u32> {
+impl<'s, 'b: 's, U: Supports<Index>> IntoIndexBufferDataU32<'s>
+  for ArrayBufferRange<'b, u32, U>
+{
   fn into_index_buffer_data_u32(self) -> BufferSlice<'s> {
-    self.slice(..)
+    self.slice()
   }
 }
 impl<'s> Deref for RenderPass<'s> {
@@ -0,0 +1,135 @@
+use wgpu::{
+  AddressMode, CompareFunction, FilterMode, Sampler, SamplerBorderColor,
+  SamplerDescriptor,
+};
+
+use super::controller::WGPUController;
+
+// Common samplers, built once per controller and shared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SamplerPreset {
+  LinearClamp,
+  LinearRepeat,
+  LinearMirror,
+  NearestClamp,
+  NearestRepeat,
+  NearestMirror,
+}
+
+impl SamplerPreset {
+  pub fn filter(&self) -> FilterMode {
+    match self {
+      SamplerPreset::LinearClamp
+      | SamplerPreset::LinearRepeat
+      | SamplerPreset::LinearMirror => FilterMode::Linear,
+      SamplerPreset::NearestClamp
+      | SamplerPreset::NearestRepeat
+      | SamplerPreset::NearestMirror => FilterMode::Nearest,
+    }
+  }
+  pub fn address_mode(&self) -> AddressMode {
+    match self {
+      SamplerPreset::LinearClamp | SamplerPreset::NearestClamp => {
+        AddressMode::ClampToEdge
+      }
+      SamplerPreset::LinearRepeat | SamplerPreset::NearestRepeat => {
+        AddressMode::Repeat
+      }
+      SamplerPreset::LinearMirror | SamplerPreset::NearestMirror => {
+        AddressMode::MirrorRepeat
+      }
+    }
+  }
+}
+
+pub struct SamplerBuilder<'s, 'w, 'window> {
+  wgpu: &'w WGPUController<'window>,
+  descriptor: SamplerDescriptor<'s>,
+}
+
+impl<'s, 'w, 'window> SamplerBuilder<'s, 'w, 'window> {
+  pub fn new(wgpu: &'w WGPUController<'window>) -> Self {
+    Self {
+      wgpu,
+      descriptor: SamplerDescriptor::default(),
+    }
+  }
+  pub fn from_preset(
+    wgpu: &'w WGPUController<'window>,
+    preset: SamplerPreset,
+  ) -> Self {
+    Self::new(wgpu)
+      .with_filter(preset.filter())
+      .with_address_mode(preset.address_mode())
+  }
+  pub fn with_label(mut self, label: &'s str) -> Self {
+    self.descriptor.label = Some(label);
+    self
+  }
+  pub fn with_address_mode(self, address_mode: AddressMode) -> Self {
+    self
+      .with_address_mode_u(address_mode)
+      .with_address_mode_v(address_mode)
+      .with_address_mode_w(address_mode)
+  }
+  pub fn with_address_mode_u(mut self, address_mode: AddressMode) -> Self {
+    self.descriptor.address_mode_u = address_mode;
+    self
+  }
+  pub fn with_address_mode_v(mut self, address_mode: AddressMode) -> Self {
+    self.descriptor.address_mode_v = address_mode;
+    self
+  }
+  pub fn with_address_mode_w(mut self, address_mode: AddressMode) -> Self {
+    self.descriptor.address_mode_w = address_mode;
+    self
+  }
+  // Sets the magnification, minification and mipmap filters together.
+  pub fn with_filter(self, filter: FilterMode) -> Self {
+    self
+      .with_mag_filter(filter)
+      .with_min_filter(filter)
+      .with_mipmap_filter(filter)
+  }
+  pub fn with_mag_filter(mut self, filter: FilterMode) -> Self {
+    self.descriptor.mag_filter = filter;
+    self
+  }
+  pub fn with_min_filter(mut self, filter: FilterMode) -> Self {
+    self.descriptor.min_filter = filter;
+    self
+  }
+  pub fn with_mipmap_filter(mut self, filter: FilterMode) -> Self {
+    self.descriptor.mipmap_filter = filter;
+    self
+  }
+  pub fn with_lod_clamp(mut self, min: f32, max: f32) -> Self {
+    self.descriptor.lod_min_clamp = min;
+    self.descriptor.lod_max_clamp = max;
+    self
+  }
+  // Makes this a comparison sampler, which has to be bound with
+  // SamplerBindingType::Comparison.
+  pub fn with_compare(mut self, compare: CompareFunction) -> Self {
+    self.descriptor.compare = Some(compare);
+    self
+  }
+  // Anisotropic filtering requires every filter to be linear, so this sets
+  // them as well.
+  pub fn with_anisotropy(mut self, anisotropy_clamp: u16) -> Self {
+    self.descriptor.anisotropy_clamp = anisotropy_clamp;
+    if anisotropy_clamp > 1 {
+      self = self.with_filter(FilterMode::Linear);
+    }
+    self
+  }
+  // Only used with AddressMode::ClampToBorder, which needs the
+  // ADDRESS_MODE_CLAMP_TO_BORDER feature.
+  pub fn with_border_color(mut self, border_color: SamplerBorderColor) -> Self {
+    self.descriptor.border_color = Some(border_color);
+    self
+  }
+  pub fn build(self) -> Sampler {
+    self.wgpu.device.create_sampler(&self.descriptor)
+  }
+}
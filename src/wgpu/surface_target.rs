@@ -0,0 +1,111 @@
+use std::{cell::RefCell, rc::Rc};
+
+use wgpu::TextureView;
+
+use super::{
+  bind::{BindGroupLayout, BindGroupWithLayout},
+  controller::WGPUController,
+  texture::Texture,
+};
+
+// The size of a render target relative to the surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SurfaceSize {
+  Full,
+  Half,
+  Scaled(f32),
+}
+
+impl SurfaceSize {
+  pub fn dimensions(&self, width: u32, height: u32) -> (u32, u32) {
+    let scale = |length: u32| match self {
+      SurfaceSize::Full => length,
+      SurfaceSize::Half => length / 2,
+      SurfaceSize::Scaled(scale) => (length as f32 * scale).round() as u32,
+    };
+    (scale(width).max(1), scale(height).max(1))
+  }
+}
+
+#[derive(Debug)]
+pub(crate) struct SurfaceTargetState {
+  size: SurfaceSize,
+  texture: RefCell<Texture>,
+}
+
+impl SurfaceTargetState {
+  #[track_caller]
+  pub(crate) fn resize(&self, wgpu: &WGPUController, width: u32, height: u32) {
+    let (width, height) = self.size.dimensions(width, height);
+    self.texture.borrow_mut().resize(wgpu, width, height);
+  }
+}
+
+// A texture the controller recreates whenever the surface is resized. Views
+// and textures are handed out by value, so they should be fetched again each
+// frame rather than stored.
+#[derive(Debug, Clone)]
+pub struct SurfaceTarget {
+  state: Rc<SurfaceTargetState>,
+}
+
+impl SurfaceTarget {
+  pub(crate) fn new(size: SurfaceSize, texture: Texture) -> Self {
+    Self {
+      state: Rc::new(SurfaceTargetState {
+        size,
+        texture: RefCell::new(texture),
+      }),
+    }
+  }
+  pub(crate) fn state(&self) -> &Rc<SurfaceTargetState> {
+    &self.state
+  }
+  pub fn size(&self) -> SurfaceSize {
+    self.state.size
+  }
+  pub fn view(&self) -> TextureView {
+    self.state.texture.borrow().view().clone()
+  }
+  pub fn texture(&self) -> wgpu::Texture {
+    self.state.texture.borrow().texture().clone()
+  }
+  pub fn width(&self) -> u32 {
+    self.state.texture.borrow().width()
+  }
+  pub fn height(&self) -> u32 {
+    self.state.texture.borrow().height()
+  }
+}
+
+// A bind group that references surface targets, rebuilt from `build` the
+// first time it's used after a resize. Identical layouts are compatible, so
+// pipelines made with the original layout keep working.
+pub struct SurfaceBindGroup {
+  build: Box<dyn Fn(&WGPUController) -> BindGroupWithLayout>,
+  bind_group: BindGroupWithLayout,
+  generation: usize,
+}
+
+impl SurfaceBindGroup {
+  pub fn new(
+    wgpu: &WGPUController,
+    build: impl Fn(&WGPUController) -> BindGroupWithLayout + 'static,
+  ) -> Self {
+    Self {
+      bind_group: build(wgpu),
+      build: Box::new(build),
+      generation: wgpu.surface_generation(),
+    }
+  }
+  pub fn layout(&self) -> &BindGroupLayout {
+    &self.bind_group.layout
+  }
+  pub fn get(&mut self, wgpu: &WGPUController) -> &BindGroupWithLayout {
+    if self.generation != wgpu.surface_generation() {
+      self.bind_group = (self.build)(wgpu);
+      self.generation = wgpu.surface_generation();
+    }
+    &self.bind_group
+  }
+}
@@ -1,10 +1,259 @@
+use std::ops::Deref;
+
 use wgpu::{
-  Extent3d, Texture, TextureDescriptor, TextureDimension, TextureFormat,
-  TextureUsages,
+  Extent3d, Features, Origin3d, TexelCopyBufferLayout, TextureDescriptor,
+  TextureDimension, TextureFormat, TextureUsages, TextureView,
+  TextureViewDescriptor, TextureViewDimension, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use super::{
+  controller::WGPUController,
+  image::TextureData,
+  ping_pong::PingPongTexture,
+  registry::ResourceToken,
+  surface_target::{SurfaceSize, SurfaceTarget},
 };
 
-use super::controller::WGPUController;
+// The view dimension a default view of the whole texture gets.
+pub(crate) fn default_view_dimension(
+  texture: &wgpu::Texture,
+) -> TextureViewDimension {
+  match texture.dimension() {
+    TextureDimension::D1 => TextureViewDimension::D1,
+    TextureDimension::D2 if texture.depth_or_array_layers() > 1 => {
+      TextureViewDimension::D2Array
+    }
+    TextureDimension::D2 => TextureViewDimension::D2,
+    TextureDimension::D3 => TextureViewDimension::D3,
+  }
+}
+
+// Anything that can be bound as a texture, along with the view dimension its
+// bind group layout entry should declare. Plain views can't report their
+// dimension, so they're assumed to cover the whole texture.
+pub trait TextureViewSource<'v>: Copy {
+  fn texture_view(self) -> &'v TextureView;
+  fn view_dimension(&self) -> TextureViewDimension;
+}
+
+impl<'v> TextureViewSource<'v> for &'v TextureView {
+  fn texture_view(self) -> &'v TextureView {
+    self
+  }
+  fn view_dimension(&self) -> TextureViewDimension {
+    default_view_dimension(self.texture())
+  }
+}
+
+impl<'v> TextureViewSource<'v> for &'v Texture {
+  fn texture_view(self) -> &'v TextureView {
+    &self.view
+  }
+  fn view_dimension(&self) -> TextureViewDimension {
+    Texture::view_dimension(self)
+  }
+}
+
+impl<'v> TextureViewSource<'v> for &'v DimensionedView {
+  fn texture_view(self) -> &'v TextureView {
+    &self.view
+  }
+  fn view_dimension(&self) -> TextureViewDimension {
+    self.dimension
+  }
+}
+
+// A view of part of a texture, such as one layer or cube face, which remembers
+// its dimension for bind group layouts.
+#[derive(Debug, Clone)]
+pub struct DimensionedView {
+  pub view: TextureView,
+  pub dimension: TextureViewDimension,
+}
+
+impl Deref for DimensionedView {
+  type Target = TextureView;
+  fn deref(&self) -> &Self::Target {
+    &self.view
+  }
+}
+
+impl<'v> From<&'v DimensionedView> for &'v TextureView {
+  fn from(view: &'v DimensionedView) -> Self {
+    &view.view
+  }
+}
+
+// Cube map faces in the order of their array layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+  PositiveX,
+  NegativeX,
+  PositiveY,
+  NegativeY,
+  PositiveZ,
+  NegativeZ,
+}
 
+impl CubeFace {
+  pub const ALL: [CubeFace; 6] = [
+    CubeFace::PositiveX,
+    CubeFace::NegativeX,
+    CubeFace::PositiveY,
+    CubeFace::NegativeY,
+    CubeFace::PositiveZ,
+    CubeFace::NegativeZ,
+  ];
+  pub fn layer(&self) -> u32 {
+    *self as u32
+  }
+}
+
+// A texture along with the descriptor it was created from and a default view
+// of it, so it can be resized and passed anywhere a view is expected.
+#[derive(Debug)]
+pub struct Texture {
+  texture: wgpu::Texture,
+  view: TextureView,
+  descriptor: wgpu::wgt::TextureDescriptor<Option<String>, Vec<TextureFormat>>,
+  view_dimension: Option<TextureViewDimension>,
+  _token: Option<ResourceToken>,
+}
+
+impl Texture {
+  #[track_caller]
+  pub fn new(wgpu: &WGPUController, descriptor: &TextureDescriptor) -> Self {
+    Self::new_with_view_dimension(wgpu, descriptor, None)
+  }
+  // The default view gets `view_dimension` instead of the one inferred from
+  // the texture's shape, which is needed for cube maps and arrays with a
+  // single layer.
+  #[track_caller]
+  pub fn new_with_view_dimension(
+    wgpu: &WGPUController,
+    descriptor: &TextureDescriptor,
+    view_dimension: Option<TextureViewDimension>,
+  ) -> Self {
+    let texture = wgpu.device.create_texture(descriptor);
+    Self {
+      view: texture.create_view(&TextureViewDescriptor {
+        dimension: view_dimension,
+        ..Default::default()
+      }),
+      view_dimension,
+      texture,
+      descriptor: descriptor.map_label_and_view_formats(
+        |label| label.map(str::to_owned),
+        |view_formats| view_formats.to_vec(),
+      ),
+      _token: wgpu.register_texture(descriptor),
+    }
+  }
+  pub fn texture(&self) -> &wgpu::Texture {
+    &self.texture
+  }
+  pub fn view(&self) -> &TextureView {
+    &self.view
+  }
+  pub fn view_dimension(&self) -> TextureViewDimension {
+    self
+      .view_dimension
+      .unwrap_or_else(|| default_view_dimension(&self.texture))
+  }
+  pub fn create_dimensioned_view(
+    &self,
+    descriptor: &TextureViewDescriptor,
+  ) -> DimensionedView {
+    DimensionedView {
+      view: self.texture.create_view(descriptor),
+      dimension: descriptor
+        .dimension
+        .unwrap_or_else(|| self.view_dimension()),
+    }
+  }
+  // A 2D view of one array layer or cube face.
+  pub fn layer_view(&self, layer: u32) -> DimensionedView {
+    assert_eq!(
+      self.dimension(),
+      TextureDimension::D2,
+      "only 2D textures have layers"
+    );
+    self.create_dimensioned_view(&TextureViewDescriptor {
+      dimension: Some(TextureViewDimension::D2),
+      base_array_layer: layer,
+      array_layer_count: Some(1),
+      ..Default::default()
+    })
+  }
+  pub fn face_view(&self, face: CubeFace) -> DimensionedView {
+    self.layer_view(face.layer())
+  }
+  // A view of a single mip level, as render attachments need.
+  pub fn mip_view(&self, mip_level: u32) -> DimensionedView {
+    self.create_dimensioned_view(&TextureViewDescriptor {
+      dimension: self.view_dimension,
+      base_mip_level: mip_level,
+      mip_level_count: Some(1),
+      ..Default::default()
+    })
+  }
+  pub fn descriptor(&self) -> TextureDescriptor<'_> {
+    TextureDescriptor {
+      label: self.descriptor.label.as_deref(),
+      size: self.descriptor.size,
+      mip_level_count: self.descriptor.mip_level_count,
+      sample_count: self.descriptor.sample_count,
+      dimension: self.descriptor.dimension,
+      format: self.descriptor.format,
+      usage: self.descriptor.usage,
+      view_formats: &self.descriptor.view_formats,
+    }
+  }
+  // Copies the first mip level back to the CPU, which needs COPY_SRC usage.
+  pub fn read(&self, wgpu: &WGPUController) -> TextureData {
+    self.read_mip_level(wgpu, 0)
+  }
+  pub fn read_mip_level(
+    &self,
+    wgpu: &WGPUController,
+    mip_level: u32,
+  ) -> TextureData {
+    TextureData::read(wgpu, &self.texture, mip_level)
+  }
+  // Recreates the texture at the new size, keeping the rest of its
+  // descriptor. Anything referencing the old texture or view, such as bind
+  // groups, has to be rebuilt.
+  #[track_caller]
+  pub fn resize(&mut self, wgpu: &WGPUController, width: u32, height: u32) {
+    let (width, height) = (width.max(1), height.max(1));
+    if width == self.width() && height == self.height() {
+      return;
+    }
+    let mut descriptor = self.descriptor();
+    descriptor.size.width = width;
+    descriptor.size.height = height;
+    descriptor.mip_level_count = descriptor
+      .mip_level_count
+      .min(descriptor.size.max_mips(descriptor.dimension));
+    *self =
+      Self::new_with_view_dimension(wgpu, &descriptor, self.view_dimension);
+  }
+}
+
+impl Deref for Texture {
+  type Target = wgpu::Texture;
+  fn deref(&self) -> &Self::Target {
+    &self.texture
+  }
+}
+
+impl<'t> From<&'t Texture> for &'t TextureView {
+  fn from(texture: &'t Texture) -> Self {
+    &texture.view
+  }
+}
+
+#[derive(Clone)]
 pub struct TextureBuilder<'s, 'w, 'window> {
   wgpu: &'w WGPUController<'window>,
   label: Option<&'s str>,
@@ -15,6 +264,7 @@ pub struct TextureBuilder<'s, 'w, 'window> {
   format: TextureFormat,
   usage: TextureUsages,
   view_formats: Vec<TextureFormat>,
+  view_dimension: Option<TextureViewDimension>,
 }
 
 impl<'s, 'w, 'window> TextureBuilder<'s, 'w, 'window> {
@@ -36,10 +286,91 @@ impl<'s, 'w, 'window> TextureBuilder<'s, 'w, 'window> {
       dimension: TextureDimension::D2,
       format: TextureFormat::Bgra8UnormSrgb,
       usage: wgpu::TextureUsages::TEXTURE_BINDING
-        | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        | wgpu::TextureUsages::RENDER_ATTACHMENT
+        | wgpu::TextureUsages::COPY_SRC,
       view_formats: vec![],
+      view_dimension: None,
     }
   }
+  pub fn new_3d(
+    wgpu: &'w WGPUController<'window>,
+    width: u32,
+    height: u32,
+    depth: u32,
+  ) -> Self {
+    let mut builder = Self::new_2d(wgpu, width, height)
+      .with_format(TextureFormat::Rgba8Unorm)
+      .with_usage(
+        TextureUsages::TEXTURE_BINDING
+          | TextureUsages::COPY_SRC
+          | TextureUsages::COPY_DST,
+      );
+    builder.size.depth_or_array_layers = depth;
+    builder.dimension = TextureDimension::D3;
+    builder
+  }
+  // Depth textures can be rendered to and sampled, but not copied, since
+  // Depth24Plus formats have no defined layout.
+  pub fn new_depth(
+    wgpu: &'w WGPUController<'window>,
+    width: u32,
+    height: u32,
+  ) -> Self {
+    Self::new_2d(wgpu, width, height)
+      .with_format(TextureFormat::Depth32Float)
+      .with_usage(
+        TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+      )
+  }
+  pub fn new_depth_stencil(
+    wgpu: &'w WGPUController<'window>,
+    width: u32,
+    height: u32,
+  ) -> Self {
+    Self::new_depth(wgpu, width, height)
+      .with_format(TextureFormat::Depth24PlusStencil8)
+  }
+  // The view dimension is set explicitly so arrays with a single layer are
+  // still bound as arrays. Layers are usually filled by copies, so unlike
+  // new_2d these can be copied to.
+  pub fn new_2d_array(
+    wgpu: &'w WGPUController<'window>,
+    width: u32,
+    height: u32,
+    layers: u32,
+  ) -> Self {
+    let mut builder = Self::new_2d(wgpu, width, height)
+      .with_view_dimension(TextureViewDimension::D2Array)
+      .with_usage(
+        TextureUsages::TEXTURE_BINDING
+          | TextureUsages::RENDER_ATTACHMENT
+          | TextureUsages::COPY_SRC
+          | TextureUsages::COPY_DST,
+      );
+    builder.size.depth_or_array_layers = layers;
+    builder
+  }
+  // Six square layers, one per CubeFace, viewed as a cube by default.
+  pub fn new_cube(wgpu: &'w WGPUController<'window>, size: u32) -> Self {
+    Self::new_2d_array(wgpu, size, size, 6)
+      .with_view_dimension(TextureViewDimension::Cube)
+  }
+  // A texture compute shaders can write to and read from. Storage textures
+  // can't be sRGB or rendered to in general, so this uses Rgba8Unorm.
+  pub fn new_storage_2d(
+    wgpu: &'w WGPUController<'window>,
+    width: u32,
+    height: u32,
+  ) -> Self {
+    Self::new_2d(wgpu, width, height)
+      .with_format(TextureFormat::Rgba8Unorm)
+      .with_usage(
+        TextureUsages::STORAGE_BINDING
+          | TextureUsages::TEXTURE_BINDING
+          | TextureUsages::COPY_SRC
+          | TextureUsages::COPY_DST,
+      )
+  }
   pub fn with_label(mut self, label: &'s str) -> Self {
     self.label = Some(label);
     self
@@ -48,6 +379,10 @@ impl<'s, 'w, 'window> TextureBuilder<'s, 'w, 'window> {
     self.mip_level_count = mip_level_count;
     self
   }
+  pub fn with_full_mip_chain(mut self) -> Self {
+    self.mip_level_count = self.size.max_mips(self.dimension);
+    self
+  }
   pub fn with_sample_count(mut self, sample_count: u32) -> Self {
     self.sample_count = sample_count;
     self
@@ -60,6 +395,17 @@ impl<'s, 'w, 'window> TextureBuilder<'s, 'w, 'window> {
     self.usage = usage;
     self
   }
+  pub fn with_storage_binding(mut self) -> Self {
+    self.usage |= TextureUsages::STORAGE_BINDING;
+    self
+  }
+  pub fn with_view_dimension(
+    mut self,
+    view_dimension: TextureViewDimension,
+  ) -> Self {
+    self.view_dimension = Some(view_dimension);
+    self
+  }
   pub fn with_view_formats(mut self, view_formats: Vec<TextureFormat>) -> Self {
     self.view_formats = view_formats;
     self
@@ -68,16 +414,161 @@ impl<'s, 'w, 'window> TextureBuilder<'s, 'w, 'window> {
     self.view_formats.push(view_format);
     self
   }
+  // Builds a texture sized relative to the surface, which the controller
+  // recreates on resize. The builder's own size is ignored.
+  #[track_caller]
+  pub fn build_surface_target(mut self, size: SurfaceSize) -> SurfaceTarget {
+    let wgpu = self.wgpu;
+    (self.size.width, self.size.height) =
+      size.dimensions(wgpu.config.width, wgpu.config.height);
+    let target = SurfaceTarget::new(size, self.build());
+    wgpu.register_surface_target(&target);
+    target
+  }
+  #[track_caller]
+  pub fn build_ping_pong(self, size: SurfaceSize) -> PingPongTexture {
+    PingPongTexture::new(
+      self.clone().build_surface_target(size),
+      self.build_surface_target(size),
+    )
+  }
+  #[track_caller]
   pub fn build(self) -> Texture {
-    self.wgpu.device.create_texture(&TextureDescriptor {
-      label: self.label,
-      size: self.size,
-      mip_level_count: self.mip_level_count,
-      sample_count: self.sample_count,
-      dimension: self.dimension,
-      format: self.format,
-      usage: self.usage,
-      view_formats: &self.view_formats,
-    })
+    let features = self.wgpu.device.features();
+    if self.usage.contains(TextureUsages::STORAGE_BINDING)
+      && !features.contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+    {
+      assert!(
+        self
+          .format
+          .guaranteed_format_features(features)
+          .allowed_usages
+          .contains(TextureUsages::STORAGE_BINDING),
+        "{:?} textures can't have STORAGE_BINDING usage, set a storage \
+        format such as Rgba8Unorm with with_format",
+        self.format
+      );
+    }
+    Texture::new_with_view_dimension(
+      self.wgpu,
+      &TextureDescriptor {
+        label: self.label,
+        size: self.size,
+        mip_level_count: self.mip_level_count,
+        sample_count: self.sample_count,
+        dimension: self.dimension,
+        format: self.format,
+        usage: self.usage,
+        view_formats: &self.view_formats,
+      },
+      self.view_dimension,
+    )
+  }
+}
+
+// Describes a mip level of a texture as it's laid out in a buffer, with each
+// row padded to COPY_BYTES_PER_ROW_ALIGNMENT as buffer/texture copies require.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureCopyLayout {
+  pub size: Extent3d,
+  pub bytes_per_row: u32,
+  pub padded_bytes_per_row: u32,
+  pub rows_per_image: u32,
+  pub block_height: u32,
+}
+
+impl TextureCopyLayout {
+  pub fn new(texture: &wgpu::Texture, mip_level: u32) -> Self {
+    let format = texture.format();
+    let size = texture
+      .size()
+      .mip_level_size(mip_level, texture.dimension());
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format.block_copy_size(None).expect(
+      "textures with multiple aspects can't be copied to or from buffers as a \
+      whole",
+    );
+    let bytes_per_row = size.width.div_ceil(block_width) * block_size;
+    Self {
+      size,
+      bytes_per_row,
+      padded_bytes_per_row: bytes_per_row
+        .next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT),
+      rows_per_image: size.height.div_ceil(block_height),
+      block_height,
+    }
+  }
+  pub fn rows(&self) -> u32 {
+    self.rows_per_image * self.size.depth_or_array_layers
+  }
+  pub fn size_in_bytes(&self) -> u64 {
+    self.bytes_per_row as u64 * self.rows() as u64
+  }
+  pub fn padded_size_in_bytes(&self) -> u64 {
+    self.padded_bytes_per_row as u64 * self.rows() as u64
+  }
+  pub fn buffer_layout(&self, offset: u64) -> TexelCopyBufferLayout {
+    TexelCopyBufferLayout {
+      offset,
+      bytes_per_row: Some(self.padded_bytes_per_row),
+      rows_per_image: Some(self.rows_per_image),
+    }
+  }
+  // The copies that move the mip level to or from a tightly packed buffer.
+  // Aligned rows go in a single copy, otherwise each row is copied on its own,
+  // since single-row copies don't need an aligned bytes_per_row.
+  pub fn packed_copies(
+    &self,
+    offset: u64,
+  ) -> Vec<(TexelCopyBufferLayout, Origin3d, Extent3d)> {
+    if self
+      .bytes_per_row
+      .is_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT)
+    {
+      let layout = TexelCopyBufferLayout {
+        offset,
+        bytes_per_row: Some(self.bytes_per_row),
+        rows_per_image: Some(self.rows_per_image),
+      };
+      return vec![(layout, Origin3d::ZERO, self.size)];
+    }
+    (0..self.size.depth_or_array_layers)
+      .flat_map(|z| (0..self.rows_per_image).map(move |row| (z, row)))
+      .map(|(z, row)| {
+        let y = row * self.block_height;
+        let layout = TexelCopyBufferLayout {
+          offset: offset
+            + (z * self.rows_per_image + row) as u64
+              * self.bytes_per_row as u64,
+          bytes_per_row: None,
+          rows_per_image: None,
+        };
+        let size = Extent3d {
+          width: self.size.width,
+          height: self.block_height.min(self.size.height - y),
+          depth_or_array_layers: 1,
+        };
+        (layout, Origin3d { x: 0, y, z }, size)
+      })
+      .collect()
+  }
+  pub fn pad_rows(&self, data: &[u8], padded: &mut [u8]) {
+    let (row, padded_row) = (
+      self.bytes_per_row as usize,
+      self.padded_bytes_per_row as usize,
+    );
+    for (source, destination) in
+      data.chunks(row).zip(padded.chunks_mut(padded_row))
+    {
+      destination[..source.len()].copy_from_slice(source);
+    }
+  }
+  pub fn unpad_rows(&self, padded: &[u8]) -> Vec<u8> {
+    padded
+      .chunks(self.padded_bytes_per_row as usize)
+      .take(self.rows() as usize)
+      .flat_map(|row| &row[..self.bytes_per_row as usize])
+      .copied()
+      .collect()
   }
 }
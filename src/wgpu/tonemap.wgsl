@@ -0,0 +1,74 @@
+struct Params {
+  exposure: f32,
+  tonemap: u32,
+  dither: u32,
+  surface_is_srgb: u32,
+}
+
+@group(0) @binding(0) var scene: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> params: Params;
+
+// A single triangle covering the whole target.
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+  let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+  return vec4f(uv * vec2f(2., -2.) + vec2f(-1., 1.), 0., 1.);
+}
+
+// Narkowicz's fit of the ACES filmic curve.
+fn aces(x: vec3f) -> vec3f {
+  return (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+}
+
+fn linear_to_srgb(c: vec3f) -> vec3f {
+  return select(
+    1.055 * pow(c, vec3f(1. / 2.4)) - 0.055,
+    c * 12.92,
+    c <= vec3f(0.0031308)
+  );
+}
+
+fn srgb_to_linear(c: vec3f) -> vec3f {
+  return select(
+    pow((c + 0.055) / 1.055, vec3f(2.4)),
+    c / 12.92,
+    c <= vec3f(0.04045)
+  );
+}
+
+fn gradient_noise(p: vec2f) -> f32 {
+  return fract(52.9829189 * fract(dot(p, vec2f(0.06711056, 0.00583715))));
+}
+
+@fragment
+fn fragment(@builtin(position) position: vec4f) -> @location(0) vec4f {
+  let hdr = max(
+    textureLoad(scene, vec2u(position.xy), 0).rgb * params.exposure,
+    vec3f(0.)
+  );
+  var color: vec3f;
+  switch params.tonemap {
+    case 0u: {
+      color = hdr / (hdr + 1.);
+    }
+    case 1u: {
+      color = aces(hdr);
+    }
+    default: {
+      color = hdr;
+    }
+  }
+  // Dithering is applied to the 8-bit encoded value, so it's done in sRGB
+  // space and converted back when the surface encodes it again.
+  var encoded = linear_to_srgb(clamp(color, vec3f(0.), vec3f(1.)));
+  if params.dither != 0u {
+    let noise = gradient_noise(position.xy)
+      + gradient_noise(position.xy + vec2f(47., 17.))
+      - 1.;
+    encoded = clamp(encoded + noise / 255., vec3f(0.), vec3f(1.));
+  }
+  if params.surface_is_srgb != 0u {
+    return vec4f(srgb_to_linear(encoded), 1.);
+  }
+  return vec4f(encoded, 1.);
+}